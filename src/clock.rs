@@ -17,6 +17,15 @@ impl Expiry {
     pub fn subsec_nanos(&self) -> u32 {
         self.0.subsec_nanos()
     }
+
+    // 指定した時刻から満了までの時間を返す. 満了済みなら 0 を返す.
+    pub fn duration_from(&self, now: Expiry) -> Duration {
+        if self.0 > now.0 {
+            self.0 - now.0
+        } else {
+            Duration::new(0, 0)
+        }
+    }
 }
 
 impl Default for Expiry {
@@ -1,4 +1,4 @@
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut};
 use io_service::{IoObject, FromRawFd, IoService, IoActor, Handler, AsyncResult};
 use traits::{Protocol, IoControl, GetSocketOption, SetSocketOption, Shutdown};
 use fd_ops::*;
@@ -31,24 +31,48 @@ impl<P: Protocol> DgramSocket<P> {
         async_recv(self, buf, flags, handler)
     }
 
+    pub fn async_receive_vectored<F>(&self, bufs: &mut [IoSliceMut], flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_recvmsg(self, bufs, flags, handler)
+    }
+
     pub fn async_receive_from<F>(&self, buf: &mut [u8], flags: i32, handler: F) -> F::Output
         where F: Handler<(usize, P::Endpoint)>,
     {
         async_recvfrom(self, buf, flags, unsafe { self.pro.uninitialized() }, handler)
     }
 
+    pub fn async_receive_from_vectored<F>(&self, bufs: &mut [IoSliceMut], flags: i32, handler: F) -> F::Output
+        where F: Handler<(usize, P::Endpoint)>,
+    {
+        async_recvmsg_from(self, bufs, flags, unsafe { self.pro.uninitialized() }, handler)
+    }
+
     pub fn async_send<F>(&self, buf: &[u8], flags: i32, handler: F) -> F::Output
         where F: Handler<usize>,
     {
         async_send(self, buf, flags, handler)
     }
 
+    pub fn async_send_vectored<F>(&self, bufs: &[IoSlice], flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_sendmsg(self, bufs, flags, handler)
+    }
+
     pub fn async_send_to<F>(&self, buf: &[u8], flags: i32, ep: P::Endpoint, handler: F) -> F::Output
         where F: Handler<usize>,
     {
         async_sendto(self, buf, flags, ep, handler)
     }
 
+    pub fn async_send_to_vectored<F>(&self, bufs: &[IoSlice], flags: i32, ep: P::Endpoint, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_sendmsg_to(self, bufs, flags, ep, handler)
+    }
+
     pub fn available(&self) -> io::Result<usize> {
         let mut bytes = BytesReadable::default();
         try!(self.io_control(&mut bytes));
@@ -95,10 +119,18 @@ impl<P: Protocol> DgramSocket<P> {
         recv(self, buf, flags)
     }
 
+    pub fn receive_vectored(&self, bufs: &mut [IoSliceMut], flags: i32) -> io::Result<usize> {
+        recvmsg(self, bufs, flags)
+    }
+
     pub fn receive_from(&self, buf: &mut [u8], flags: i32) -> io::Result<(usize, P::Endpoint)> {
         recvfrom(self, buf, flags, unsafe { self.pro.uninitialized() })
     }
 
+    pub fn receive_from_vectored(&self, bufs: &mut [IoSliceMut], flags: i32) -> io::Result<(usize, P::Endpoint)> {
+        recvmsg_from(self, bufs, flags, unsafe { self.pro.uninitialized() })
+    }
+
     pub fn remote_endpoint(&self) -> io::Result<P::Endpoint> {
         getpeername(self, unsafe { self.pro.uninitialized() })
     }
@@ -107,10 +139,18 @@ impl<P: Protocol> DgramSocket<P> {
         send(self, buf, flags)
     }
 
+    pub fn send_vectored(&self, bufs: &[IoSlice], flags: i32) -> io::Result<usize> {
+        sendmsg(self, bufs, flags)
+    }
+
     pub fn send_to(&self, buf: &[u8], flags: i32, ep: P::Endpoint) -> io::Result<usize> {
         sendto(self, buf, flags, ep)
     }
 
+    pub fn send_to_vectored(&self, bufs: &[IoSlice], flags: i32, ep: P::Endpoint) -> io::Result<usize> {
+        sendmsg_to(self, bufs, flags, ep)
+    }
+
     pub fn set_non_blocking(&self, on: bool) -> io::Result<()> {
         setnonblock(self, on)
     }
@@ -126,6 +166,36 @@ impl<P: Protocol> DgramSocket<P> {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl<P: Protocol> DgramSocket<P> {
+    /// Asynchronously receives up to `bufs.len()` datagrams in one system call.
+    ///
+    /// Each datagram is received into its own buffer. The handler receives the number of datagrams,
+    /// and `msgs` is filled with the length and the sender of each of them in the same order.
+    pub fn async_receive_many<F>(&self, bufs: &mut [IoSliceMut], msgs: &mut Vec<(usize, P::Endpoint)>, flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_recvmmsg(self, bufs, msgs, flags, unsafe { self.pro.uninitialized() }, handler)
+    }
+
+    /// Asynchronously sends each message as a datagram to its endpoint in one system call.
+    ///
+    /// The handler receives the number of datagrams sent, which may be less than `msgs.len()`.
+    pub fn async_send_many<F>(&self, msgs: &[(&[u8], P::Endpoint)], flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_sendmmsg(self, msgs, flags, handler)
+    }
+
+    pub fn receive_many(&self, bufs: &mut [IoSliceMut], msgs: &mut Vec<(usize, P::Endpoint)>, flags: i32) -> io::Result<usize> {
+        recvmmsg(self, bufs, msgs, flags, unsafe { self.pro.uninitialized() })
+    }
+
+    pub fn send_many(&self, msgs: &[(&[u8], P::Endpoint)], flags: i32) -> io::Result<usize> {
+        sendmmsg(self, msgs, flags)
+    }
+}
+
 unsafe impl<P: Protocol> IoObject for DgramSocket<P> {
     fn io_service(&self) -> &IoService {
         self.act.io_service()
@@ -153,3 +223,85 @@ impl<P: Protocol> AsIoActor for DgramSocket<P> {
         &self.act
     }
 }
+
+#[test]
+fn test_vectored_send_to() {
+    use ip::{Udp, UdpEndpoint, UdpSocket, IpAddrV4};
+
+    let io = &IoService::new();
+    let ep = UdpEndpoint::new(IpAddrV4::loopback(), 0);
+    let rx = UdpSocket::new(io, Udp::v4()).unwrap();
+    rx.bind(&ep).unwrap();
+    let ep = rx.local_endpoint().unwrap();
+    let tx = UdpSocket::new(io, Udp::v4()).unwrap();
+    tx.bind(&UdpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+
+    assert_eq!(tx.send_to_vectored(&[IoSlice::new(b"head"), IoSlice::new(b"payload")], 0, ep).unwrap(), 11);
+    let mut head = [0; 4];
+    let mut body = [0; 16];
+    let (len, from) = rx.receive_from_vectored(&mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut body)], 0).unwrap();
+    assert_eq!(len, 11);
+    assert_eq!(from, tx.local_endpoint().unwrap());
+    assert_eq!(&head, b"head");
+    assert_eq!(&body[..7], b"payload");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_receive_many() {
+    use std::sync::{Arc, Mutex};
+    use ip::{Udp, UdpEndpoint, UdpSocket, IpAddrV4};
+    use wrap;
+
+    let io = &IoService::new();
+    let rx = Arc::new(UdpSocket::new(io, Udp::v4()).unwrap());
+    rx.bind(&UdpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+    let ep = rx.local_endpoint().unwrap();
+    let tx = UdpSocket::new(io, Udp::v4()).unwrap();
+    tx.bind(&UdpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+    let from = tx.local_endpoint().unwrap();
+
+    let msgs = [(&b"one"[..], ep.clone()), (&b"two!"[..], ep.clone()), (&b"three"[..], ep.clone())];
+    assert_eq!(tx.send_many(&msgs, 0).unwrap(), 3);
+
+    let mut buf1 = [0; 8];
+    let mut buf2 = [0; 8];
+    let mut res = Vec::new();
+    assert_eq!(rx.receive_many(&mut [IoSliceMut::new(&mut buf1), IoSliceMut::new(&mut buf2)], &mut res, 0).unwrap(), 2);
+    assert_eq!(res, vec![(3, from.clone()), (4, from.clone())]);
+    assert_eq!(&buf1[..3], b"one");
+    assert_eq!(&buf2[..4], b"two!");
+
+    let count = Arc::new(Mutex::new(None));
+    let count_ = count.clone();
+    let mut bufs = [IoSliceMut::new(&mut buf1), IoSliceMut::new(&mut buf2)];
+    rx.async_receive_many(&mut bufs, &mut res, 0, wrap(move |_: Arc<UdpSocket>, r: io::Result<usize>| {
+        *count_.lock().unwrap() = Some(r);
+    }, &rx));
+    io.run();
+    assert_eq!(count.lock().unwrap().take().unwrap().unwrap(), 1);
+    assert_eq!(res, vec![(5, from)]);
+    assert_eq!(&buf1[..5], b"three");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_receive_many_partial() {
+    use ip::{Udp, UdpEndpoint, UdpSocket, IpAddrV4};
+
+    let io = &IoService::new();
+    let rx = UdpSocket::new(io, Udp::v4()).unwrap();
+    rx.bind(&UdpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+    let ep = rx.local_endpoint().unwrap();
+    let tx = UdpSocket::new(io, Udp::v4()).unwrap();
+    tx.bind(&UdpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+    assert_eq!(tx.send_many(&[(&b"one"[..], ep.clone()), (&b"two"[..], ep)], 0).unwrap(), 2);
+
+    let mut buf1 = [0; 8];
+    let mut buf2 = [0; 8];
+    let mut buf3 = [0; 8];
+    let mut res = Vec::new();
+    let mut bufs = [IoSliceMut::new(&mut buf1), IoSliceMut::new(&mut buf2), IoSliceMut::new(&mut buf3)];
+    assert_eq!(rx.receive_many(&mut bufs, &mut res, 0).unwrap(), 2);
+    assert_eq!(res.len(), 2);
+}
@@ -26,6 +26,28 @@ pub const EAGAIN: ErrCode = ErrCode(Errno(libc::EAGAIN));
 pub const ECANCELED: ErrCode = ErrCode(Errno(libc::ECANCELED));
 pub const EINTR: ErrCode = ErrCode(Errno(libc::EINTR));
 pub const EINPROGRESS: ErrCode = ErrCode(Errno(libc::EINPROGRESS));
+pub const ETIMEDOUT: ErrCode = ErrCode(Errno(libc::ETIMEDOUT));
+
+impl ErrCode {
+    // completion で作った完了の結果なら、その値を返す.
+    pub fn completed(&self) -> Option<usize> {
+        if (self.0).0 < 0 {
+            Some((-1 - (self.0).0) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+// リアクタが実行した操作の結果 (戻り値、または errno を負にした値) をハンドラに渡すエラーコードにする.
+// 0 以上の結果は errno と区別できるように負の値で表す.
+pub fn completion(res: i32) -> ErrCode {
+    if res < 0 {
+        ErrCode(Errno(-res))
+    } else {
+        ErrCode(Errno(-1 - res))
+    }
+}
 
 pub fn last_error() -> ErrCode {
     ErrCode(errno::errno())
@@ -13,11 +13,11 @@ pub fn cancel<T>(fd: &T)
     let io = fd.io_service();
 
     for handler in fd.as_io_actor().del_input() {
-        io.post(|io| handler(io, ECANCELED));
+        io.post(|io| handler.call(io, ECANCELED));
     }
 
     for handler in fd.as_io_actor().del_output() {
-        io.post(|io| handler(io, ECANCELED));
+        io.post(|io| handler.call(io, ECANCELED));
     }
 }
 
@@ -1,8 +1,18 @@
-use std::io;
-use libc::{self, F_GETFL, F_SETFL, O_NONBLOCK, c_void, ssize_t, sockaddr, socklen_t};
+use std::io::{self, IoSlice, IoSliceMut};
+use std::mem;
+use std::ptr;
+#[cfg(target_os = "linux")]
+use std::cell::{Cell, UnsafeCell};
+use libc::{self, F_GETFL, F_SETFL, O_NONBLOCK, SOL_SOCKET, SCM_RIGHTS, MSG_CTRUNC, c_void, c_int, c_uint, ssize_t, sockaddr, socklen_t, iovec, msghdr, cmsghdr};
+#[cfg(target_os = "linux")]
+use libc::mmsghdr;
+#[cfg(target_os = "linux")]
+use errno::{Errno, set_errno};
+#[cfg(target_os = "linux")]
+use ip::{UDP_SEGMENT, UDP_GRO};
 use unsafe_cell::{UnsafeRefCell, UnsafeSliceCell};
 use error::{ErrCode, READY, EINTR, EAGAIN, EINPROGRESS, last_error, stopped, eof, write_zero};
-use io_service::{Handler, AsyncResult};
+use io_service::{Handler, AsyncResult, IoActor, ReactorOp};
 use traits::{Protocol, SockAddr, IoControl, Shutdown, GetSocketOption, SetSocketOption};
 use super::{RawFd, AsRawFd, AsIoActor};
 
@@ -43,6 +53,14 @@ pub fn bind<T: AsRawFd, E: SockAddr>(fd: &T, ep: &E) -> io::Result<()> {
     Ok(())
 }
 
+// アドレスを指定せずに bind して、カーネルに抽象名前空間のアドレスを割り当てさせる.
+#[cfg(target_os = "linux")]
+pub fn autobind<T: AsRawFd>(fd: &T) -> io::Result<()> {
+    let sa_family = libc::AF_UNIX as libc::sa_family_t;
+    libc_try!(libc::bind(fd.as_raw_fd(), &sa_family as *const _ as *const sockaddr, mem::size_of_val(&sa_family) as socklen_t));
+    Ok(())
+}
+
 pub fn listen<T: AsRawFd>(fd: &T, backlog: u32) -> io::Result<()> {
     libc_try!(libc::listen(fd.as_raw_fd(), backlog as i32));
     Ok(())
@@ -64,7 +82,7 @@ pub fn getpeername<T: AsRawFd, E: SockAddr>(fd: &T, mut ep: E) -> io::Result<E>
 
 pub fn getsockopt<T: AsRawFd, P: Protocol, C: GetSocketOption<P>>(fd: &T, pro: &P) -> io::Result<C> {
     let mut cmd = C::default();
-    let mut datalen = 0;
+    let mut datalen = cmd.capacity() as socklen_t;
     libc_try!(libc::getsockopt(fd.as_raw_fd(), cmd.level(pro), cmd.name(pro), cmd.data_mut() as *mut _ as *mut c_void, &mut datalen));
     cmd.resize(datalen as usize);
     Ok(cmd)
@@ -99,6 +117,12 @@ pub fn async_connect_detail<T, E, F>(fd: &T, ep: &E, handler: F)
           F: Handler<()>,
 {
     let io = fd.io_service();
+    let op = connect_op(ep);
+    if !io.stopped() && fd.as_io_actor().performs(&op) {
+        async_connect_submit(fd, op, handler, READY);
+        return;
+    }
+
     let mode = getnonblock(fd).unwrap();
     setnonblock(fd, true).unwrap();
     if !io.stopped() {
@@ -137,6 +161,40 @@ pub fn async_connect_detail<T, E, F>(fd: &T, ep: &E, handler: F)
     io.post(move |io| handler.callback(io, Err(stopped())));
 }
 
+// リアクタに投入する connect. アドレスは操作が完了するまでリアクタが保持する.
+fn connect_op<E: SockAddr>(ep: &E) -> ReactorOp {
+    let mut ss: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let len = ep.size();
+    debug_assert!(len <= mem::size_of_val(&ss));
+    unsafe { ptr::copy_nonoverlapping(ep.as_sockaddr() as *const _ as *const u8, &mut ss as *mut _ as *mut u8, len) };
+    ReactorOp::Connect(ss, len as socklen_t)
+}
+
+// ec が READY なら新しい接続として積み、EINPROGRESS なら待ち行列の先頭でリアクタに投入する.
+fn async_connect_submit<T, F>(fd: &T, op: ReactorOp, handler: F, ec: ErrCode)
+    where T: AsIoActor,
+          F: Handler<()>,
+{
+    let fd_ptr = UnsafeRefCell::new(fd);
+    let addr = match op {
+        ReactorOp::Connect(ss, len) => (ss, len),
+        _ => unreachable!(),
+    };
+    fd.as_io_actor().submit_output(handler.wrap(move |io, ec, handler| {
+        let fd = unsafe { fd_ptr.as_ref() };
+        match ec {
+            READY => async_connect_submit(fd, ReactorOp::Connect(addr.0, addr.1), handler, EINPROGRESS),
+            ec => {
+                fd.as_io_actor().next_output();
+                handler.callback(io, match ec.completed() {
+                    Some(_) => Ok(()),
+                    None => Err(ec.into()),
+                });
+            },
+        }
+    }), op, ec);
+}
+
 pub fn async_connect<T, E, F>(fd: &T, ep: &E, handler: F) -> F::Output
     where T: AsIoActor,
           E: SockAddr,
@@ -170,16 +228,37 @@ pub fn accept<T, E>(fd: &T, mut ep: E) -> io::Result<(RawFd, E)>
     Err(stopped())
 }
 
+// ec が EINPROGRESS なら、待ち行列の先頭で accept をリアクタに投入する.
 fn async_accept_detail<T, E, F>(fd: &T, mut ep: E, handler: F, ec: ErrCode)
     where T: AsIoActor,
           E: SockAddr,
           F: Handler<(RawFd, E)>,
 {
+    let submit = ec != EAGAIN && fd.as_io_actor().performs(&ReactorOp::Accept);
     let fd_ptr = UnsafeRefCell::new(fd);
-    fd.as_io_actor().add_input(handler.wrap(move |io, ec, handler| {
+    let callback = handler.wrap(move |io, ec, handler| {
         let fd = unsafe { fd_ptr.as_ref() };
+        if let Some(acc) = ec.completed() {
+            fd.as_io_actor().next_input();
+            // リアクタは相手のアドレスを返さないので、受け付けたソケットから得る.
+            let acc = acc as RawFd;
+            let mut socklen = ep.capacity() as socklen_t;
+            if unsafe { libc::getpeername(acc, ep.as_mut_sockaddr() as *mut _ as *mut sockaddr, &mut socklen) } != 0 {
+                let ec = last_error();
+                unsafe { libc::close(acc) };
+                handler.callback(io, Err(ec.into()));
+                return;
+            }
+            unsafe { ep.resize(socklen as usize); }
+            handler.callback(io, Ok((acc, ep)));
+            return;
+        }
         match ec {
             READY => {
+                if fd.as_io_actor().performs(&ReactorOp::Accept) {
+                    async_accept_detail(fd, ep, handler, EINPROGRESS);
+                    return;
+                }
                 let mode = getnonblock(fd).unwrap();
                 setnonblock(fd, true).unwrap();
 
@@ -214,12 +293,19 @@ fn async_accept_detail<T, E, F>(fd: &T, mut ep: E, handler: F, ec: ErrCode)
                 setnonblock(fd, mode).unwrap();
                 handler.callback(io, Err(stopped()));
             },
+            // 非ブロッキングの記述子に投入した accept は EAGAIN で完了するので、準備ができるのを待ってから投入し直す.
+            EAGAIN => async_accept_detail(fd, ep, handler, EAGAIN),
             ec => {
                 fd.as_io_actor().next_input();
                 handler.callback(io, Err(ec.into()));
             },
         }
-    }), ec);
+    });
+    if submit {
+        fd.as_io_actor().submit_input(callback, ReactorOp::Accept, ec);
+    } else {
+        fd.as_io_actor().add_input(callback, ec);
+    }
 }
 
 pub fn async_accept<T, E, F>(fd: &T, ep: E, handler: F) -> F::Output
@@ -237,6 +323,23 @@ trait Reader : Send + 'static {
     type Output;
     unsafe fn read(&mut self, fd: RawFd, buf: &mut [u8]) -> ssize_t;
     fn ok(self, len: ssize_t) -> Self::Output;
+
+    // リアクタに実行させられる操作なら、それを返す.
+    fn op(&self, _buf: &mut [u8]) -> Option<ReactorOp> {
+        None
+    }
+
+    // EAGAIN が fd 以外の原因なら、その IoActor と入力側を待つかどうかを返す. 操作はそちらの準備を待ってからやり直す.
+    fn blocked_on(&self) -> Option<(&IoActor, bool)> {
+        None
+    }
+}
+
+// 記述子の準備ができているかを調べる. timeout が負なら準備ができるまで待つ.
+// 相手側が閉じている場合も操作の結果で分かるので true を返す.
+fn poll_ready(fd: RawFd, input: bool, timeout: c_int) -> bool {
+    let mut pfd = libc::pollfd { fd: fd, events: if input { libc::POLLIN } else { libc::POLLOUT }, revents: 0 };
+    unsafe { libc::poll(&mut pfd, 1, timeout) > 0 }
 }
 
 fn read_detail<T, R>(fd: &T, buf: &mut [u8], mut reader: R) -> io::Result<R::Output>
@@ -252,6 +355,12 @@ fn read_detail<T, R>(fd: &T, buf: &mut [u8], mut reader: R) -> io::Result<R::Out
             return Err(eof());
         }
         let ec = last_error();
+        if ec == EAGAIN {
+            if let Some((act, input)) = reader.blocked_on() {
+                poll_ready(act.as_raw_fd(), input, -1);
+                continue;
+            }
+        }
         if ec != EINTR {
             return Err(ec.into());
         }
@@ -259,18 +368,32 @@ fn read_detail<T, R>(fd: &T, buf: &mut [u8], mut reader: R) -> io::Result<R::Out
     Err(stopped())
 }
 
+// ec が EINPROGRESS なら、待ち行列の先頭で操作をリアクタに投入する.
 fn async_read_detail<T, R, F>(fd: &T, buf: &mut [u8], mut reader: R, handler: F, ec: ErrCode)
     where T: AsIoActor,
           R: Reader,
           F: Handler<R::Output>,
 {
+    let op = match reader.op(buf) {
+        Some(op) if ec != EAGAIN && fd.as_io_actor().performs(&op) => Some(op),
+        _ => None,
+    };
     let fd_ptr = UnsafeRefCell::new(fd);
     let mut buf_ptr = UnsafeSliceCell::new(buf);
-    fd.as_io_actor().add_input(handler.wrap(move |io, ec, handler| {
+    let callback = handler.wrap(move |io, ec, handler| {
         let fd = unsafe { fd_ptr.as_ref() };
+        if let Some(len) = ec.completed() {
+            fd.as_io_actor().next_input();
+            handler.callback(io, if len > 0 { Ok(reader.ok(len as ssize_t)) } else { Err(eof()) });
+            return;
+        }
         match ec {
             READY => {
                 let buf = unsafe { buf_ptr.as_mut_slice() };
+                if reader.op(buf).map_or(false, |op| fd.as_io_actor().performs(&op)) {
+                    async_read_detail(fd, buf, reader, handler, EINPROGRESS);
+                    return;
+                }
                 let mode = getnonblock(fd).unwrap();
                 setnonblock(fd, true).unwrap();
 
@@ -291,7 +414,13 @@ fn async_read_detail<T, R, F>(fd: &T, buf: &mut [u8], mut reader: R, handler: F,
                     let ec = last_error();
                     if ec == EAGAIN {
                         setnonblock(fd, mode).unwrap();
-                        async_read_detail(fd, buf, reader, handler, ec);
+                        match reader.blocked_on().map(|(act, input)| (UnsafeRefCell::new(act), input)) {
+                            Some((act, input)) => {
+                                fd.as_io_actor().next_input();
+                                async_read_blocked(fd, buf, reader, handler, unsafe { act.as_ref() }, input);
+                            },
+                            None => async_read_detail(fd, buf, reader, handler, ec),
+                        }
                         return;
                     }
                     if ec != EINTR {
@@ -305,12 +434,43 @@ fn async_read_detail<T, R, F>(fd: &T, buf: &mut [u8], mut reader: R, handler: F,
                 setnonblock(fd, mode).unwrap();
                 handler.callback(io, Err(stopped()));
             },
+            // 非ブロッキングの記述子に投入した操作は EAGAIN で完了するので、準備ができるのを待ってから投入し直す.
+            EAGAIN => async_read_detail(fd, unsafe { buf_ptr.as_mut_slice() }, reader, handler, EAGAIN),
             ec => {
                 fd.as_io_actor().next_input();
                 handler.callback(io, Err(ec.into()));
             },
         }
-    }), ec);
+    });
+    match op {
+        Some(op) => fd.as_io_actor().submit_input(callback, op, ec),
+        None => fd.as_io_actor().add_input(callback, ec),
+    }
+}
+
+// 他の記述子の準備を待ってから async_read_detail をやり直す.
+fn async_read_blocked<T, R, F>(fd: &T, buf: &mut [u8], reader: R, handler: F, act: &IoActor, input: bool)
+    where T: AsIoActor,
+          R: Reader,
+          F: Handler<R::Output>,
+{
+    let fd_ptr = UnsafeRefCell::new(fd);
+    let mut buf_ptr = UnsafeSliceCell::new(buf);
+    let act_ptr = UnsafeRefCell::new(act);
+    let callback = handler.wrap(move |io, ec, handler| {
+        let (fd, buf, act) = unsafe { (fd_ptr.as_ref(), buf_ptr.as_mut_slice(), act_ptr.as_ref()) };
+        // まだ準備できていなければ、待ち行列を進めずに待ち直す.
+        if ec == READY && !poll_ready(act.as_raw_fd(), input, 0) {
+            async_read_blocked(fd, buf, reader, handler, act, input);
+            return;
+        }
+        if input { act.next_input() } else { act.next_output() }
+        match ec {
+            READY => async_read_detail(fd, buf, reader, handler, READY),
+            ec => handler.callback(io, Err(ec.into())),
+        }
+    });
+    if input { act.add_input(callback, EAGAIN) } else { act.add_output(callback, EAGAIN) }
 }
 
 
@@ -326,6 +486,10 @@ impl Reader for Read {
     fn ok(self, len: ssize_t) -> Self::Output {
         len as usize
     }
+
+    fn op(&self, buf: &mut [u8]) -> Option<ReactorOp> {
+        Some(ReactorOp::Read(buf.as_mut_ptr(), buf.len()))
+    }
 }
 
 pub fn read<T>(fd: &T, buf: &mut [u8]) -> io::Result<usize>
@@ -412,11 +576,380 @@ pub fn async_recvfrom<T, E, F>(fd: &T, buf: &mut [u8], flags: i32,   ep: E, hand
 }
 
 
+// iovec は生ポインタを持つので、スレッド間で転送できるように包む.
+struct IoVec(Vec<iovec>);
+
+unsafe impl Send for IoVec {
+}
+
+impl IoVec {
+    fn from_mut(bufs: &mut [IoSliceMut]) -> IoVec {
+        IoVec(bufs.iter_mut().map(|buf| iovec {
+            iov_base: buf.as_mut_ptr() as *mut c_void,
+            iov_len: buf.len(),
+        }).collect())
+    }
+
+    fn from_ref(bufs: &[IoSlice]) -> IoVec {
+        IoVec(bufs.iter().map(|buf| iovec {
+            iov_base: buf.as_ptr() as *mut c_void,
+            iov_len: buf.len(),
+        }).collect())
+    }
+
+    unsafe fn msghdr(&self) -> msghdr {
+        let mut msg: msghdr = mem::zeroed();
+        msg.msg_iov = self.0.as_ptr() as *mut iovec;
+        msg.msg_iovlen = self.0.len() as _;
+        msg
+    }
+}
+
+
+struct ReadV { iov: IoVec }
+
+impl Reader for ReadV {
+    type Output = usize;
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        libc::readv(fd, self.iov.0.as_ptr(), self.iov.0.len() as c_int)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+
+    fn op(&self, _: &mut [u8]) -> Option<ReactorOp> {
+        Some(ReactorOp::ReadV(self.iov.0.as_ptr(), self.iov.0.len()))
+    }
+}
+
+pub fn readv<T>(fd: &T, bufs: &mut [IoSliceMut]) -> io::Result<usize>
+    where T: AsIoActor,
+{
+    read_detail(fd, &mut [], ReadV { iov: IoVec::from_mut(bufs) })
+}
+
+pub fn async_readv<T, F>(fd: &T, bufs: &mut [IoSliceMut], handler: F) -> F::Output
+    where T: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_read_detail(fd, &mut [], ReadV { iov: IoVec::from_mut(bufs) }, handler, READY);
+    out.get(fd.io_service())
+}
+
+
+struct RecvMsg { iov: IoVec, flags: i32 }
+
+impl Reader for RecvMsg {
+    type Output = usize;
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        libc::recvmsg(fd, &mut msg, self.flags)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+}
+
+pub fn recvmsg<T>(fd: &T, bufs: &mut [IoSliceMut], flags: i32) -> io::Result<usize>
+    where T: AsIoActor,
+{
+    read_detail(fd, &mut [], RecvMsg { iov: IoVec::from_mut(bufs), flags: flags })
+}
+
+pub fn async_recvmsg<T, F>(fd: &T, bufs: &mut [IoSliceMut], flags: i32, handler: F) -> F::Output
+    where T: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_read_detail(fd, &mut [], RecvMsg { iov: IoVec::from_mut(bufs), flags: flags }, handler, READY);
+    out.get(fd.io_service())
+}
+
+
+struct RecvMsgFrom<E> { iov: IoVec, flags: i32, ep: E, socklen: socklen_t }
+
+impl<E: SockAddr + Send> Reader for RecvMsgFrom<E> {
+    type Output = (usize, E);
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        msg.msg_name = self.ep.as_mut_sockaddr() as *mut _ as *mut c_void;
+        msg.msg_namelen = self.ep.capacity() as socklen_t;
+        let len = libc::recvmsg(fd, &mut msg, self.flags);
+        self.socklen = msg.msg_namelen;
+        len
+    }
+
+    fn ok(mut self, len: ssize_t) -> Self::Output {
+        unsafe { self.ep.resize(self.socklen as usize); }
+        (len as usize, self.ep)
+    }
+}
+
+pub fn recvmsg_from<T, E>(fd: &T, bufs: &mut [IoSliceMut], flags: i32, ep: E) -> io::Result<(usize, E)>
+    where T: AsIoActor,
+          E: SockAddr,
+{
+    let socklen = ep.capacity() as socklen_t;
+    read_detail(fd, &mut [], RecvMsgFrom { iov: IoVec::from_mut(bufs), flags: flags, ep: ep, socklen: socklen })
+}
+
+pub fn async_recvmsg_from<T, E, F>(fd: &T, bufs: &mut [IoSliceMut], flags: i32, ep: E, handler: F) -> F::Output
+    where T: AsIoActor,
+          E: SockAddr,
+          F: Handler<(usize, E)>,
+{
+    let out = handler.async_result();
+    let socklen = ep.capacity() as socklen_t;
+    async_read_detail(fd, &mut [], RecvMsgFrom { iov: IoVec::from_mut(bufs), flags: flags, ep: ep, socklen: socklen }, handler, READY);
+    out.get(fd.io_service())
+}
+
+// SCM_RIGHTS で一度に受け取れる記述子の最大数 (Linux の SCM_MAX_FD).
+const MAX_FDS: usize = 253;
+
+#[cfg(target_os = "linux")]
+const CMSG_FLAGS: i32 = libc::MSG_CMSG_CLOEXEC;
+
+#[cfg(not(target_os = "linux"))]
+const CMSG_FLAGS: i32 = 0;
+
+// MSG_CMSG_CLOEXEC がない環境では受け取った後に設定する.
+#[cfg(target_os = "linux")]
+fn set_cloexec(_: &[RawFd]) {
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_cloexec(fds: &[RawFd]) {
+    for &fd in fds {
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+}
+
+// 補助データのバッファ. cmsghdr のアラインメントを満たすために u64 で確保する.
+struct CmsgBuf(Vec<u64>, usize);
+
+impl CmsgBuf {
+    fn with_capacity(len: usize) -> CmsgBuf {
+        let len = unsafe { libc::CMSG_SPACE(len as c_uint) } as usize;
+        CmsgBuf(vec![0; (len + 7) / 8], len)
+    }
+
+    fn new<T: Copy>(level: c_int, ty: c_int, data: &[T]) -> CmsgBuf {
+        let len = data.len() * mem::size_of::<T>();
+        let mut buf = Self::with_capacity(len);
+        unsafe {
+            let mut msg: msghdr = mem::zeroed();
+            buf.set(&mut msg);
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = ty;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(len as c_uint) as _;
+            ptr::copy_nonoverlapping(data.as_ptr(), libc::CMSG_DATA(cmsg) as *mut T, data.len());
+        }
+        buf
+    }
+
+    fn rights(fds: &[RawFd]) -> CmsgBuf {
+        if fds.is_empty() {
+            return CmsgBuf(Vec::new(), 0);
+        }
+        Self::new(SOL_SOCKET, SCM_RIGHTS, fds)
+    }
+
+    unsafe fn set(&mut self, msg: &mut msghdr) {
+        if self.1 > 0 {
+            msg.msg_control = self.0.as_mut_ptr() as *mut c_void;
+            msg.msg_controllen = self.1 as _;
+        }
+    }
+
+    // 受け取った補助データを走査する.
+    unsafe fn each<F>(msg: &msghdr, mut func: F)
+        where F: FnMut(&cmsghdr, *const u8, usize)
+    {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+        while !cmsg.is_null() {
+            let data = libc::CMSG_DATA(cmsg) as *const u8;
+            let len = (*cmsg).cmsg_len as usize - (data as usize - cmsg as usize);
+            func(&*cmsg, data, len);
+            cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+        }
+    }
+}
+
+fn close_fds(fds: &mut Vec<RawFd>) {
+    for fd in fds.drain(..) {
+        unsafe { libc::close(fd) };
+    }
+}
+
+
+struct RecvFds { iov: IoVec, flags: i32, cmsg: CmsgBuf, fds: Vec<RawFd>, truncated: bool }
+
+impl Reader for RecvFds {
+    type Output = (usize, Vec<RawFd>, bool);
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        self.cmsg.set(&mut msg);
+        let len = libc::recvmsg(fd, &mut msg, self.flags | CMSG_FLAGS);
+        if len < 0 {
+            return len;
+        }
+        let fds = &mut self.fds;
+        CmsgBuf::each(&msg, |cmsg, data, len| if cmsg.cmsg_level == SOL_SOCKET && cmsg.cmsg_type == SCM_RIGHTS {
+            let data = data as *const RawFd;
+            for i in 0..(len / mem::size_of::<RawFd>()) {
+                fds.push(ptr::read_unaligned(data.offset(i as isize)));
+            }
+        });
+        set_cloexec(fds);
+        // 一部しか受け取れなかった記述子は閉じて、受信したデータだけを返す.
+        if msg.msg_flags & MSG_CTRUNC != 0 {
+            close_fds(fds);
+            self.truncated = true;
+        }
+        if len == 0 {
+            close_fds(fds);
+        }
+        len
+    }
+
+    fn ok(mut self, len: ssize_t) -> Self::Output {
+        (len as usize, mem::replace(&mut self.fds, Vec::new()), self.truncated)
+    }
+}
+
+impl Drop for RecvFds {
+    fn drop(&mut self) {
+        close_fds(&mut self.fds)
+    }
+}
+
+fn recv_fds_reader(bufs: &mut [IoSliceMut], flags: i32, max_fds: usize) -> RecvFds {
+    RecvFds {
+        iov: IoVec::from_mut(bufs),
+        flags: flags,
+        cmsg: CmsgBuf::with_capacity(max_fds * mem::size_of::<RawFd>()),
+        fds: Vec::new(),
+        truncated: false,
+    }
+}
+
+pub fn recvmsg_fds<T>(fd: &T, buf: &mut [u8], flags: i32) -> io::Result<(usize, Vec<RawFd>, bool)>
+    where T: AsIoActor,
+{
+    read_detail(fd, &mut [], recv_fds_reader(&mut [IoSliceMut::new(buf)], flags, MAX_FDS))
+}
+
+pub fn async_recvmsg_fds<T, F>(fd: &T, buf: &mut [u8], flags: i32, handler: F) -> F::Output
+    where T: AsIoActor,
+          F: Handler<(usize, Vec<RawFd>, bool)>,
+{
+    let out = handler.async_result();
+    async_read_detail(fd, &mut [], recv_fds_reader(&mut [IoSliceMut::new(buf)], flags, MAX_FDS), handler, READY);
+    out.get(fd.io_service())
+}
+
+#[cfg(target_os = "linux")]
+struct RecvCreds<E, C> { iov: IoVec, flags: i32, ep: E, socklen: socklen_t, cmsg: CmsgBuf, cred: Option<C> }
+
+#[cfg(target_os = "linux")]
+impl<E: SockAddr + Send, C: From<libc::ucred> + Send + 'static> Reader for RecvCreds<E, C> {
+    type Output = (usize, E, Option<C>);
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        msg.msg_name = self.ep.as_mut_sockaddr() as *mut _ as *mut c_void;
+        msg.msg_namelen = self.ep.capacity() as socklen_t;
+        self.cmsg.set(&mut msg);
+        let len = libc::recvmsg(fd, &mut msg, self.flags | CMSG_FLAGS);
+        if len < 0 {
+            return len;
+        }
+        self.socklen = msg.msg_namelen;
+        let cred = &mut self.cred;
+        *cred = None;
+        CmsgBuf::each(&msg, |cmsg, data, len| if cmsg.cmsg_level == SOL_SOCKET {
+            if cmsg.cmsg_type == libc::SCM_CREDENTIALS && len >= mem::size_of::<libc::ucred>() {
+                *cred = Some(C::from(ptr::read_unaligned(data as *const libc::ucred)));
+            } else if cmsg.cmsg_type == SCM_RIGHTS {
+                // 受け取るつもりのない記述子は閉じる.
+                let data = data as *const RawFd;
+                for i in 0..(len / mem::size_of::<RawFd>()) {
+                    libc::close(ptr::read_unaligned(data.offset(i as isize)));
+                }
+            }
+        });
+        len
+    }
+
+    fn ok(mut self, len: ssize_t) -> Self::Output {
+        unsafe { self.ep.resize(self.socklen as usize); }
+        (len as usize, self.ep, self.cred)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn recv_creds_reader<E: SockAddr, C>(buf: &mut [u8], flags: i32, ep: E) -> RecvCreds<E, C> {
+    RecvCreds {
+        iov: IoVec::from_mut(&mut [IoSliceMut::new(buf)]),
+        flags: flags,
+        socklen: ep.capacity() as socklen_t,
+        ep: ep,
+        cmsg: CmsgBuf::with_capacity(mem::size_of::<libc::ucred>() + MAX_FDS * mem::size_of::<RawFd>()),
+        cred: None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn recvmsg_creds<T, E, C>(fd: &T, buf: &mut [u8], flags: i32, ep: E) -> io::Result<(usize, E, Option<C>)>
+    where T: AsIoActor,
+          E: SockAddr,
+          C: From<libc::ucred> + Send + 'static,
+{
+    read_detail(fd, &mut [], recv_creds_reader(buf, flags, ep))
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_recvmsg_creds<T, E, C, F>(fd: &T, buf: &mut [u8], flags: i32, ep: E, handler: F) -> F::Output
+    where T: AsIoActor,
+          E: SockAddr,
+          C: From<libc::ucred> + Send + 'static,
+          F: Handler<(usize, E, Option<C>)>,
+{
+    let out = handler.async_result();
+    async_read_detail(fd, &mut [], recv_creds_reader(buf, flags, ep), handler, READY);
+    out.get(fd.io_service())
+}
+
+
 
 trait Writer : Send + 'static{
     type Output;
     unsafe fn write(&self, fd: RawFd, buf: &[u8]) -> ssize_t;
     fn ok(self, len: ssize_t) -> Self::Output;
+
+    // リアクタに実行させられる操作なら、それを返す.
+    fn op(&self, _buf: &[u8]) -> Option<ReactorOp> {
+        None
+    }
+
+    // 0 バイトの書き込みを正常な完了とするなら true を返す. sendfile で入力が終端に達した場合など.
+    fn accept_zero(&self) -> bool {
+        false
+    }
+
+    // EAGAIN が fd 以外の原因なら、その IoActor と入力側を待つかどうかを返す. 操作はそちらの準備を待ってからやり直す.
+    fn blocked_on(&self) -> Option<(&IoActor, bool)> {
+        None
+    }
 }
 
 fn write_detail<T, W>(fd: &T, buf: &[u8], writer: W) -> io::Result<W::Output>
@@ -425,13 +958,19 @@ fn write_detail<T, W>(fd: &T, buf: &[u8], writer: W) -> io::Result<W::Output>
 {
     while !fd.io_service().stopped() {
         let len = unsafe { writer.write(fd.as_raw_fd(), buf) };
-        if len > 0 {
+        if len > 0 || (len == 0 && writer.accept_zero()) {
             return Ok(writer.ok(len));
         }
         if len == 0 {
             return Err(write_zero());
         }
         let ec = last_error();
+        if ec == EAGAIN {
+            if let Some((act, input)) = writer.blocked_on() {
+                poll_ready(act.as_raw_fd(), input, -1);
+                continue;
+            }
+        }
         if ec != EINTR {
             return Err(ec.into());
         }
@@ -439,25 +978,38 @@ fn write_detail<T, W>(fd: &T, buf: &[u8], writer: W) -> io::Result<W::Output>
     Err(stopped())
 }
 
+// ec が EINPROGRESS なら、待ち行列の先頭で操作をリアクタに投入する.
 fn async_write_detail<T, W, F>(fd: &T, buf: &[u8], writer: W, handler: F, ec: ErrCode)
     where T: AsIoActor,
           W: Writer,
           F: Handler<W::Output>,
 {
+    let op = match writer.op(buf) {
+        Some(op) if ec != EAGAIN && fd.as_io_actor().performs(&op) => Some(op),
+        _ => None,
+    };
     let fd_ptr = UnsafeRefCell::new(fd);
     let buf_ptr = UnsafeSliceCell::new(buf);
-    fd.as_io_actor().add_output(handler.wrap(move |io, ec, handler| {
+    let callback = handler.wrap(move |io, ec, handler| {
         let fd = unsafe { fd_ptr.as_ref() };
-
+        if let Some(len) = ec.completed() {
+            fd.as_io_actor().next_output();
+            handler.callback(io, if len > 0 || writer.accept_zero() { Ok(writer.ok(len as ssize_t)) } else { Err(eof()) });
+            return;
+        }
         match ec {
             READY => {
                 let buf = unsafe { buf_ptr.as_slice() };
+                if writer.op(buf).map_or(false, |op| fd.as_io_actor().performs(&op)) {
+                    async_write_detail(fd, buf, writer, handler, EINPROGRESS);
+                    return;
+                }
                 let mode = getnonblock(fd).unwrap();
                 setnonblock(fd, true).unwrap();
 
                 while !io.stopped() {
                     let len = unsafe { writer.write(fd.as_raw_fd(), buf) };
-                    if len > 0 {
+                    if len > 0 || (len == 0 && writer.accept_zero()) {
                         fd.as_io_actor().next_output();
                         setnonblock(fd, mode).unwrap();
                         handler.callback(io, Ok(writer.ok(len)));
@@ -472,7 +1024,13 @@ fn async_write_detail<T, W, F>(fd: &T, buf: &[u8], writer: W, handler: F, ec: Er
                     let ec = last_error();
                     if ec == EAGAIN {
                         setnonblock(fd, mode).unwrap();
-                        async_write_detail(fd, buf, writer, handler, ec);
+                        match writer.blocked_on().map(|(act, input)| (UnsafeRefCell::new(act), input)) {
+                            Some((act, input)) => {
+                                fd.as_io_actor().next_output();
+                                async_write_blocked(fd, buf, writer, handler, unsafe { act.as_ref() }, input);
+                            },
+                            None => async_write_detail(fd, buf, writer, handler, ec),
+                        }
                         return;
                     }
                     if ec != EINTR {
@@ -486,12 +1044,43 @@ fn async_write_detail<T, W, F>(fd: &T, buf: &[u8], writer: W, handler: F, ec: Er
                 setnonblock(fd, mode).unwrap();
                 handler.callback(io, Err(stopped()));
             },
+            // 非ブロッキングの記述子に投入した操作は EAGAIN で完了するので、準備ができるのを待ってから投入し直す.
+            EAGAIN => async_write_detail(fd, unsafe { buf_ptr.as_slice() }, writer, handler, EAGAIN),
             ec => {
                 fd.as_io_actor().next_output();
                 handler.callback(io, Err(ec.into()));
             },
         }
-    }), ec);
+    });
+    match op {
+        Some(op) => fd.as_io_actor().submit_output(callback, op, ec),
+        None => fd.as_io_actor().add_output(callback, ec),
+    }
+}
+
+// 他の記述子の準備を待ってから async_write_detail をやり直す.
+fn async_write_blocked<T, W, F>(fd: &T, buf: &[u8], writer: W, handler: F, act: &IoActor, input: bool)
+    where T: AsIoActor,
+          W: Writer,
+          F: Handler<W::Output>,
+{
+    let fd_ptr = UnsafeRefCell::new(fd);
+    let buf_ptr = UnsafeSliceCell::new(buf);
+    let act_ptr = UnsafeRefCell::new(act);
+    let callback = handler.wrap(move |io, ec, handler| {
+        let (fd, buf, act) = unsafe { (fd_ptr.as_ref(), buf_ptr.as_slice(), act_ptr.as_ref()) };
+        // まだ準備できていなければ、待ち行列を進めずに待ち直す.
+        if ec == READY && !poll_ready(act.as_raw_fd(), input, 0) {
+            async_write_blocked(fd, buf, writer, handler, act, input);
+            return;
+        }
+        if input { act.next_input() } else { act.next_output() }
+        match ec {
+            READY => async_write_detail(fd, buf, writer, handler, READY),
+            ec => handler.callback(io, Err(ec.into())),
+        }
+    });
+    if input { act.add_input(callback, EAGAIN) } else { act.add_output(callback, EAGAIN) }
 }
 
 
@@ -507,6 +1096,10 @@ impl Writer for Write {
     fn ok(self, len: ssize_t) -> Self::Output {
         len as usize
     }
+
+    fn op(&self, buf: &[u8]) -> Option<ReactorOp> {
+        Some(ReactorOp::Write(buf.as_ptr(), buf.len()))
+    }
 }
 
 pub fn write<T>(fd: &T, buf: &[u8]) -> io::Result<usize>
@@ -587,3 +1180,569 @@ pub fn async_sendto<T, E, F>(fd: &T, buf: &[u8], flags: i32, ep: E, handler: F)
     async_write_detail(fd, buf, SendTo { flags: flags, ep: ep }, handler, READY);
     out.get(fd.io_service())
 }
+
+
+struct WriteV { iov: IoVec }
+
+impl Writer for WriteV {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        libc::writev(fd, self.iov.0.as_ptr(), self.iov.0.len() as c_int)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+
+    fn op(&self, _: &[u8]) -> Option<ReactorOp> {
+        Some(ReactorOp::WriteV(self.iov.0.as_ptr(), self.iov.0.len()))
+    }
+}
+
+pub fn writev<T>(fd: &T, bufs: &[IoSlice]) -> io::Result<usize>
+    where T: AsIoActor,
+{
+    write_detail(fd, &[], WriteV { iov: IoVec::from_ref(bufs) })
+}
+
+pub fn async_writev<T, F>(fd: &T, bufs: &[IoSlice], handler: F) -> F::Output
+    where T: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], WriteV { iov: IoVec::from_ref(bufs) }, handler, READY);
+    out.get(fd.io_service())
+}
+
+
+struct SendMsg { iov: IoVec, flags: i32 }
+
+impl Writer for SendMsg {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        let msg = self.iov.msghdr();
+        libc::sendmsg(fd, &msg, self.flags)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+}
+
+pub fn sendmsg<T>(fd: &T, bufs: &[IoSlice], flags: i32) -> io::Result<usize>
+    where T: AsIoActor,
+{
+    write_detail(fd, &[], SendMsg { iov: IoVec::from_ref(bufs), flags: flags })
+}
+
+pub fn async_sendmsg<T, F>(fd: &T, bufs: &[IoSlice], flags: i32, handler: F) -> F::Output
+    where T: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], SendMsg { iov: IoVec::from_ref(bufs), flags: flags }, handler, READY);
+    out.get(fd.io_service())
+}
+
+
+struct SendMsgTo<E> { iov: IoVec, flags: i32, ep: E }
+
+impl<E: SockAddr + Send> Writer for SendMsgTo<E> {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        msg.msg_name = self.ep.as_sockaddr() as *const _ as *mut c_void;
+        msg.msg_namelen = self.ep.size() as socklen_t;
+        libc::sendmsg(fd, &msg, self.flags)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+}
+
+pub fn sendmsg_to<T, E>(fd: &T, bufs: &[IoSlice], flags: i32, ep: E) -> io::Result<usize>
+    where T: AsIoActor,
+          E: SockAddr,
+{
+    write_detail(fd, &[], SendMsgTo { iov: IoVec::from_ref(bufs), flags: flags, ep: ep })
+}
+
+pub fn async_sendmsg_to<T, E, F>(fd: &T, bufs: &[IoSlice], flags: i32, ep: E, handler: F) -> F::Output
+    where T: AsIoActor,
+          E: SockAddr,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], SendMsgTo { iov: IoVec::from_ref(bufs), flags: flags, ep: ep }, handler, READY);
+    out.get(fd.io_service())
+}
+
+
+struct SendFds { iov: IoVec, flags: i32, cmsg: CmsgBuf }
+
+impl Writer for SendFds {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        if self.cmsg.1 > 0 {
+            msg.msg_control = self.cmsg.0.as_ptr() as *mut c_void;
+            msg.msg_controllen = self.cmsg.1 as _;
+        }
+        libc::sendmsg(fd, &msg, self.flags)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+}
+
+pub fn sendmsg_fds<T>(fd: &T, buf: &[u8], fds: &[RawFd], flags: i32) -> io::Result<usize>
+    where T: AsIoActor,
+{
+    write_detail(fd, &[], SendFds { iov: IoVec::from_ref(&[IoSlice::new(buf)]), flags: flags, cmsg: CmsgBuf::rights(fds) })
+}
+
+pub fn async_sendmsg_fds<T, F>(fd: &T, buf: &[u8], fds: &[RawFd], flags: i32, handler: F) -> F::Output
+    where T: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], SendFds { iov: IoVec::from_ref(&[IoSlice::new(buf)]), flags: flags, cmsg: CmsgBuf::rights(fds) }, handler, READY);
+    out.get(fd.io_service())
+}
+
+
+#[cfg(target_os = "linux")]
+struct SendFile { in_fd: RawFd, offset: Option<u64>, count: usize }
+
+#[cfg(target_os = "linux")]
+impl Writer for SendFile {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        // オフセットを指定した場合、入力側のファイル位置は変更しない.
+        match self.offset {
+            Some(offset) => {
+                let mut offset = offset as libc::off_t;
+                libc::sendfile(fd, self.in_fd, &mut offset, self.count)
+            },
+            None => libc::sendfile(fd, self.in_fd, ptr::null_mut(), self.count),
+        }
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+
+    fn accept_zero(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn sendfile<T>(fd: &T, in_fd: RawFd, offset: Option<u64>, count: usize) -> io::Result<usize>
+    where T: AsIoActor,
+{
+    write_detail(fd, &[], SendFile { in_fd: in_fd, offset: offset, count: count })
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_sendfile<T, F>(fd: &T, in_fd: RawFd, offset: Option<u64>, count: usize, handler: F) -> F::Output
+    where T: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], SendFile { in_fd: in_fd, offset: offset, count: count }, handler, READY);
+    out.get(fd.io_service())
+}
+
+
+// splice の EAGAIN はソケットとパイプのどちらが原因か分からないので、先にパイプを調べる.
+// パイプが準備できていなければ splice せずに EAGAIN を返し、パイプの準備を待ってからやり直す.
+#[cfg(target_os = "linux")]
+struct SpliceTo { pipe: UnsafeRefCell<IoActor>, len: usize, flags: u32, pipe_full: bool }
+
+#[cfg(target_os = "linux")]
+impl Reader for SpliceTo {
+    type Output = usize;
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        let pipe_fd = self.pipe.as_ref().as_raw_fd();
+        self.pipe_full = !poll_ready(pipe_fd, false, 0);
+        if self.pipe_full {
+            set_errno(Errno(libc::EAGAIN));
+            return -1;
+        }
+        libc::splice(fd, ptr::null_mut(), pipe_fd, ptr::null_mut(), self.len, self.flags | libc::SPLICE_F_NONBLOCK)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+
+    fn blocked_on(&self) -> Option<(&IoActor, bool)> {
+        if self.pipe_full {
+            Some((unsafe { self.pipe.as_ref() }, false))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn splice_to_reader<P: AsIoActor>(pipe: &P, len: usize, flags: u32) -> SpliceTo {
+    SpliceTo { pipe: UnsafeRefCell::new(pipe.as_io_actor()), len: len, flags: flags, pipe_full: false }
+}
+
+#[cfg(target_os = "linux")]
+pub fn splice_to<T, P>(fd: &T, pipe: &P, len: usize, flags: u32) -> io::Result<usize>
+    where T: AsIoActor,
+          P: AsIoActor,
+{
+    read_detail(fd, &mut [], splice_to_reader(pipe, len, flags))
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_splice_to<T, P, F>(fd: &T, pipe: &P, len: usize, flags: u32, handler: F) -> F::Output
+    where T: AsIoActor,
+          P: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_read_detail(fd, &mut [], splice_to_reader(pipe, len, flags), handler, READY);
+    out.get(fd.io_service())
+}
+
+
+#[cfg(target_os = "linux")]
+struct SpliceFrom { pipe: UnsafeRefCell<IoActor>, len: usize, flags: u32, pipe_empty: Cell<bool> }
+
+#[cfg(target_os = "linux")]
+impl Writer for SpliceFrom {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        let pipe_fd = self.pipe.as_ref().as_raw_fd();
+        self.pipe_empty.set(!poll_ready(pipe_fd, true, 0));
+        if self.pipe_empty.get() {
+            set_errno(Errno(libc::EAGAIN));
+            return -1;
+        }
+        libc::splice(pipe_fd, ptr::null_mut(), fd, ptr::null_mut(), self.len, self.flags | libc::SPLICE_F_NONBLOCK)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+
+    fn blocked_on(&self) -> Option<(&IoActor, bool)> {
+        if self.pipe_empty.get() {
+            Some((unsafe { self.pipe.as_ref() }, true))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn splice_from_writer<P: AsIoActor>(pipe: &P, len: usize, flags: u32) -> SpliceFrom {
+    SpliceFrom { pipe: UnsafeRefCell::new(pipe.as_io_actor()), len: len, flags: flags, pipe_empty: Cell::new(false) }
+}
+
+#[cfg(target_os = "linux")]
+pub fn splice_from<T, P>(fd: &T, pipe: &P, len: usize, flags: u32) -> io::Result<usize>
+    where T: AsIoActor,
+          P: AsIoActor,
+{
+    write_detail(fd, &[], splice_from_writer(pipe, len, flags))
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_splice_from<T, P, F>(fd: &T, pipe: &P, len: usize, flags: u32, handler: F) -> F::Output
+    where T: AsIoActor,
+          P: AsIoActor,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], splice_from_writer(pipe, len, flags), handler, READY);
+    out.get(fd.io_service())
+}
+
+
+// mmsghdr は生ポインタを持ち、カーネルが msg_len を書き込むので UnsafeCell で包む.
+#[cfg(target_os = "linux")]
+struct MMsgHdr(UnsafeCell<Vec<mmsghdr>>);
+
+#[cfg(target_os = "linux")]
+unsafe impl Send for MMsgHdr {
+}
+
+#[cfg(target_os = "linux")]
+impl MMsgHdr {
+    fn new(iov: &IoVec) -> MMsgHdr {
+        MMsgHdr(UnsafeCell::new(iov.0.iter().map(|iov| {
+            let mut hdr: mmsghdr = unsafe { mem::zeroed() };
+            hdr.msg_hdr.msg_iov = iov as *const _ as *mut _;
+            hdr.msg_hdr.msg_iovlen = 1;
+            hdr
+        }).collect()))
+    }
+
+    unsafe fn as_mut(&self) -> &mut Vec<mmsghdr> {
+        &mut *self.0.get()
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct RecvMMsg<E> { _iov: IoVec, hdrs: MMsgHdr, eps: Vec<E>, msgs: UnsafeRefCell<Vec<(usize, E)>>, flags: i32 }
+
+#[cfg(target_os = "linux")]
+impl<E: SockAddr> Reader for RecvMMsg<E> {
+    type Output = usize;
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        let hdrs = self.hdrs.as_mut();
+        for (hdr, ep) in hdrs.iter_mut().zip(self.eps.iter_mut()) {
+            hdr.msg_hdr.msg_name = ep.as_mut_sockaddr() as *mut _ as *mut c_void;
+            hdr.msg_hdr.msg_namelen = ep.capacity() as socklen_t;
+        }
+        // ブロッキングのソケットでも最初のデータグラムが届いた時点で返る.
+        libc::recvmmsg(fd, hdrs.as_mut_ptr(), hdrs.len() as c_uint, self.flags | libc::MSG_WAITFORONE, ptr::null_mut()) as ssize_t
+    }
+
+    fn ok(mut self, len: ssize_t) -> Self::Output {
+        let hdrs = unsafe { self.hdrs.as_mut() };
+        let msgs = unsafe { self.msgs.as_mut() };
+        msgs.clear();
+        for (hdr, mut ep) in hdrs.iter().zip(self.eps.drain(..)).take(len as usize) {
+            unsafe { ep.resize(hdr.msg_hdr.msg_namelen as usize); }
+            msgs.push((hdr.msg_len as usize, ep));
+        }
+        len as usize
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn recv_mmsg<E: SockAddr>(bufs: &mut [IoSliceMut], msgs: &mut Vec<(usize, E)>, flags: i32, ep: E) -> RecvMMsg<E> {
+    let iov = IoVec::from_mut(bufs);
+    let hdrs = MMsgHdr::new(&iov);
+    RecvMMsg {
+        eps: vec![ep; iov.0.len()],
+        _iov: iov,
+        hdrs: hdrs,
+        msgs: UnsafeRefCell::new(msgs),
+        flags: flags,
+    }
+}
+
+/// 各バッファに1つずつデータグラムを受信し、受信した数を返す. msgs には受信サイズと送信元が入る.
+#[cfg(target_os = "linux")]
+pub fn recvmmsg<T, E>(fd: &T, bufs: &mut [IoSliceMut], msgs: &mut Vec<(usize, E)>, flags: i32, ep: E) -> io::Result<usize>
+    where T: AsIoActor,
+          E: SockAddr,
+{
+    read_detail(fd, &mut [], recv_mmsg(bufs, msgs, flags, ep))
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_recvmmsg<T, E, F>(fd: &T, bufs: &mut [IoSliceMut], msgs: &mut Vec<(usize, E)>, flags: i32, ep: E, handler: F) -> F::Output
+    where T: AsIoActor,
+          E: SockAddr,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_read_detail(fd, &mut [], recv_mmsg(bufs, msgs, flags, ep), handler, READY);
+    out.get(fd.io_service())
+}
+
+
+#[cfg(target_os = "linux")]
+struct SendMMsg { _iov: IoVec, hdrs: MMsgHdr, flags: i32 }
+
+#[cfg(target_os = "linux")]
+impl Writer for SendMMsg {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        let hdrs = self.hdrs.as_mut();
+        libc::sendmmsg(fd, hdrs.as_mut_ptr(), hdrs.len() as c_uint, self.flags) as ssize_t
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn send_mmsg<E: SockAddr>(msgs: &[(&[u8], E)], flags: i32) -> SendMMsg {
+    let iov = IoVec(msgs.iter().map(|&(buf, _)| iovec {
+        iov_base: buf.as_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    }).collect());
+    let hdrs = MMsgHdr::new(&iov);
+    for (hdr, &(_, ref ep)) in unsafe { hdrs.as_mut() }.iter_mut().zip(msgs) {
+        hdr.msg_hdr.msg_name = ep.as_sockaddr() as *const _ as *mut c_void;
+        hdr.msg_hdr.msg_namelen = ep.size() as socklen_t;
+    }
+    SendMMsg { _iov: iov, hdrs: hdrs, flags: flags }
+}
+
+/// 各メッセージを1つのデータグラムとして送信し、送信した数を返す.
+#[cfg(target_os = "linux")]
+pub fn sendmmsg<T, E>(fd: &T, msgs: &[(&[u8], E)], flags: i32) -> io::Result<usize>
+    where T: AsIoActor,
+          E: SockAddr,
+{
+    write_detail(fd, &[], send_mmsg(msgs, flags))
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_sendmmsg<T, E, F>(fd: &T, msgs: &[(&[u8], E)], flags: i32, handler: F) -> F::Output
+    where T: AsIoActor,
+          E: SockAddr,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], send_mmsg(msgs, flags), handler, READY);
+    out.get(fd.io_service())
+}
+
+
+#[cfg(target_os = "linux")]
+struct RecvGro<E> { iov: IoVec, flags: i32, ep: E, socklen: socklen_t, cmsg: CmsgBuf, segment: Option<usize> }
+
+#[cfg(target_os = "linux")]
+impl<E: SockAddr + Send> Reader for RecvGro<E> {
+    type Output = (usize, E, Option<usize>);
+
+    unsafe fn read(&mut self, fd: RawFd, _: &mut [u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        msg.msg_name = self.ep.as_mut_sockaddr() as *mut _ as *mut c_void;
+        msg.msg_namelen = self.ep.capacity() as socklen_t;
+        self.cmsg.set(&mut msg);
+        let len = libc::recvmsg(fd, &mut msg, self.flags);
+        if len < 0 {
+            return len;
+        }
+        self.socklen = msg.msg_namelen;
+        let segment = &mut self.segment;
+        *segment = None;
+        CmsgBuf::each(&msg, |cmsg, data, len| {
+            if cmsg.cmsg_level == libc::SOL_UDP && cmsg.cmsg_type == UDP_GRO && len >= mem::size_of::<c_int>() {
+                *segment = Some(ptr::read_unaligned(data as *const c_int) as usize);
+            }
+        });
+        len
+    }
+
+    fn ok(mut self, len: ssize_t) -> Self::Output {
+        unsafe { self.ep.resize(self.socklen as usize); }
+        (len as usize, self.ep, self.segment)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn recv_gro_reader<E: SockAddr>(buf: &mut [u8], flags: i32, ep: E) -> RecvGro<E> {
+    RecvGro {
+        iov: IoVec::from_mut(&mut [IoSliceMut::new(buf)]),
+        flags: flags,
+        socklen: ep.capacity() as socklen_t,
+        ep: ep,
+        cmsg: CmsgBuf::with_capacity(mem::size_of::<c_int>()),
+        segment: None,
+    }
+}
+
+/// UDP_GRO で結合されたデータグラムを受信し、結合前のセグメントサイズを返す.
+#[cfg(target_os = "linux")]
+pub fn recvmsg_gro<T, E>(fd: &T, buf: &mut [u8], flags: i32, ep: E) -> io::Result<(usize, E, Option<usize>)>
+    where T: AsIoActor,
+          E: SockAddr,
+{
+    read_detail(fd, &mut [], recv_gro_reader(buf, flags, ep))
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_recvmsg_gro<T, E, F>(fd: &T, buf: &mut [u8], flags: i32, ep: E, handler: F) -> F::Output
+    where T: AsIoActor,
+          E: SockAddr,
+          F: Handler<(usize, E, Option<usize>)>,
+{
+    let out = handler.async_result();
+    async_read_detail(fd, &mut [], recv_gro_reader(buf, flags, ep), handler, READY);
+    out.get(fd.io_service())
+}
+
+
+#[cfg(target_os = "linux")]
+struct SendGso<E> { iov: IoVec, flags: i32, ep: E, cmsg: CmsgBuf }
+
+#[cfg(target_os = "linux")]
+impl<E: SockAddr + Send> Writer for SendGso<E> {
+    type Output = usize;
+
+    unsafe fn write(&self, fd: RawFd, _: &[u8]) -> ssize_t {
+        let mut msg = self.iov.msghdr();
+        msg.msg_name = self.ep.as_sockaddr() as *const _ as *mut c_void;
+        msg.msg_namelen = self.ep.size() as socklen_t;
+        msg.msg_control = self.cmsg.0.as_ptr() as *mut c_void;
+        msg.msg_controllen = self.cmsg.1 as _;
+        libc::sendmsg(fd, &msg, self.flags)
+    }
+
+    fn ok(self, len: ssize_t) -> Self::Output {
+        len as usize
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn send_gso_writer<E: SockAddr>(buf: &[u8], flags: i32, ep: E, segment: u16) -> SendGso<E> {
+    SendGso {
+        iov: IoVec::from_ref(&[IoSlice::new(buf)]),
+        flags: flags,
+        ep: ep,
+        cmsg: CmsgBuf::new(libc::SOL_UDP, UDP_SEGMENT, &[segment]),
+    }
+}
+
+/// UDP_SEGMENT を指定し、バッファを segment バイトずつのデータグラムに分割して送信する.
+#[cfg(target_os = "linux")]
+pub fn sendmsg_gso<T, E>(fd: &T, buf: &[u8], flags: i32, ep: E, segment: u16) -> io::Result<usize>
+    where T: AsIoActor,
+          E: SockAddr,
+{
+    write_detail(fd, &[], send_gso_writer(buf, flags, ep, segment))
+}
+
+#[cfg(target_os = "linux")]
+pub fn async_sendmsg_gso<T, E, F>(fd: &T, buf: &[u8], flags: i32, ep: E, segment: u16, handler: F) -> F::Output
+    where T: AsIoActor,
+          E: SockAddr,
+          F: Handler<usize>,
+{
+    let out = handler.async_result();
+    async_write_detail(fd, &[], send_gso_writer(buf, flags, ep, segment), handler, READY);
+    out.get(fd.io_service())
+}
+
+#[test]
+fn test_recv_fds_truncated() {
+    use io_service::IoService;
+    use local::{LocalStream, LocalStreamSocket, connect_pair};
+
+    let io = &IoService::new();
+    let (tx, rx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    tx.send_fds(b"data", &[0, 1, 2], 0).unwrap();
+
+    // 記述子1つ分の補助データのバッファでは収まらないが、データは失わない.
+    let mut buf = [0; 8];
+    let (len, fds, truncated) = read_detail(&rx, &mut [], recv_fds_reader(&mut [IoSliceMut::new(&mut buf)], 0, 1)).unwrap();
+    assert_eq!(&buf[..len], b"data");
+    assert!(fds.is_empty());
+    assert!(truncated);
+}
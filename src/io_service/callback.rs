@@ -0,0 +1,394 @@
+use std::mem;
+use std::ptr;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::sync::atomic::{Ordering, AtomicBool};
+use error::ErrCode;
+use super::{IoService, CancelState};
+
+// 再利用するメモリブロックの大きさの単位 (u64 の個数). 64 バイト単位で 1024 バイトまでを再利用する.
+const BLOCK_WORDS: usize = 8;
+const MAX_CLASSES: usize = 16;
+const MAX_CACHED: usize = 64;
+
+struct Recycler {
+    classes: Vec<Vec<*mut u64>>,
+}
+
+impl Drop for Recycler {
+    fn drop(&mut self) {
+        for (i, class) in self.classes.iter().enumerate() {
+            for &ptr in class {
+                free((i + 1) * BLOCK_WORDS, ptr);
+            }
+        }
+    }
+}
+
+thread_local!(static RECYCLER: RefCell<Recycler> = RefCell::new(Recycler {
+    classes: (0..MAX_CLASSES).map(|_| Vec::new()).collect(),
+}));
+
+fn alloc(words: usize) -> *mut u64 {
+    let mut vec: Vec<u64> = Vec::with_capacity(words);
+    let ptr = vec.as_mut_ptr();
+    mem::forget(vec);
+    ptr
+}
+
+fn free(words: usize, ptr: *mut u64) {
+    unsafe { Vec::from_raw_parts(ptr, 0, words) };
+}
+
+// スレッド毎にキャッシュしているブロックを取り出す. 無ければ確保する.
+// スレッドローカル変数の破棄中や破棄後に呼ばれた場合はキャッシュを使わない.
+fn allocate(words: usize) -> *mut u64 {
+    let class = words / BLOCK_WORDS - 1;
+    if class < MAX_CLASSES {
+        if let Ok(Some(ptr)) = RECYCLER.try_with(|rec| rec.borrow_mut().classes[class].pop()) {
+            return ptr;
+        }
+    }
+    alloc(words)
+}
+
+// 解放したスレッドのキャッシュに戻す. キャッシュが一杯かキャッシュを使えなければ解放する.
+fn deallocate(words: usize, ptr: *mut u64) {
+    let class = words / BLOCK_WORDS - 1;
+    if class < MAX_CLASSES {
+        let ptr = RECYCLER.try_with(|rec| {
+            let mut rec = rec.borrow_mut();
+            let cache = &mut rec.classes[class];
+            if cache.len() < MAX_CACHED {
+                cache.push(ptr);
+                None
+            } else {
+                Some(ptr)
+            }
+        }).unwrap_or(Some(ptr));
+        if let Some(ptr) = ptr {
+            free(words, ptr);
+        }
+    } else {
+        free(words, ptr);
+    }
+}
+
+/// The reusable memory block for the handlers.
+///
+/// An asynchronous operation that is given the handler with `ArcHandler::with_memory` stores
+/// its completion handler in this block instead of allocating it.
+/// The block is released before the handler is invoked, so the handler can start the next operation
+/// with the same block.
+/// If the block is used by another operation or is too small, the handler is stored in the per-thread
+/// recycled memory as usual.
+///
+/// # Examples
+/// ```
+/// use std::io;
+/// use std::sync::Arc;
+/// use asyncio::{IoService, HandlerMemory, Stream, wrap};
+/// use asyncio::local::{LocalStream, LocalStreamSocket, connect_pair};
+///
+/// let io = &IoService::new();
+/// let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+/// let rx = Arc::new(rx);
+/// let mem = Arc::new(HandlerMemory::new(256));
+///
+/// let mut buf = [0; 8];
+/// rx.async_read_some(&mut buf, wrap(|_: Arc<LocalStreamSocket>, res: io::Result<usize>| {
+///     assert_eq!(res.unwrap(), 5);
+/// }, &rx).with_memory(&mem));
+/// assert!(mem.in_use());
+///
+/// tx.write_some(b"hello").unwrap();
+/// io.run();
+/// assert!(!mem.in_use());
+/// ```
+pub struct HandlerMemory {
+    in_use: AtomicBool,
+    words: usize,
+    ptr: *mut u64,
+}
+
+impl HandlerMemory {
+    /// Returns a new `HandlerMemory` of `size` bytes.
+    pub fn new(size: usize) -> HandlerMemory {
+        let words = (size + 7) / 8;
+        HandlerMemory {
+            in_use: AtomicBool::new(false),
+            words: words,
+            ptr: alloc(words),
+        }
+    }
+
+    /// Returns true if a handler is stored in the block.
+    pub fn in_use(&self) -> bool {
+        self.in_use.load(Ordering::SeqCst)
+    }
+}
+
+unsafe impl Send for HandlerMemory {
+}
+
+unsafe impl Sync for HandlerMemory {
+}
+
+impl Drop for HandlerMemory {
+    fn drop(&mut self) {
+        free(self.words, self.ptr);
+    }
+}
+
+enum Memory {
+    Recycled(usize),
+    Handler(Arc<HandlerMemory>),
+}
+
+impl Memory {
+    fn release(self, ptr: *mut u8) {
+        match self {
+            Memory::Recycled(words) => deallocate(words, ptr as *mut u64),
+            Memory::Handler(mem) => mem.in_use.store(false, Ordering::SeqCst),
+        }
+    }
+}
+
+struct Slot<F> {
+    mem: Memory,
+    func: F,
+}
+
+unsafe fn call_slot<F>(ptr: *mut u8, io: *const IoService, ec: ErrCode)
+    where F: FnOnce(*const IoService, ErrCode)
+{
+    // ハンドラの中で同じメモリを使えるように、呼び出す前に解放する.
+    let Slot { mem, func } = ptr::read(ptr as *mut Slot<F>);
+    mem.release(ptr);
+    func(io, ec)
+}
+
+unsafe fn drop_slot<F>(ptr: *mut u8) {
+    let Slot { mem, func } = ptr::read(ptr as *mut Slot<F>);
+    mem.release(ptr);
+    drop(func);
+}
+
+// 非同期操作の完了ハンドラ. Box<FnBox> の代わりに再利用するメモリに格納する.
+pub struct Callback {
+    ptr: *mut u8,
+    call: unsafe fn(*mut u8, *const IoService, ErrCode),
+    drop: unsafe fn(*mut u8),
+    cancel: Vec<(Arc<CancelState>, usize)>,
+}
+
+impl Callback {
+    pub fn new<F>(func: F) -> Callback
+        where F: FnOnce(*const IoService, ErrCode) + Send + 'static
+    {
+        if mem::align_of::<Slot<F>>() > mem::align_of::<u64>() {
+            // アラインメントの大きいハンドラはヒープに置いて、そのポインタを格納する.
+            let func = Box::new(func);
+            return Self::place(move |io, ec| {
+                let func = *func;
+                func(io, ec)
+            });
+        }
+        Self::place(func)
+    }
+
+    // メモリが使用中か小さすぎる場合はスレッド毎のメモリに格納する.
+    pub fn with_memory<F>(mem: &Arc<HandlerMemory>, func: F) -> Callback
+        where F: FnOnce(*const IoService, ErrCode) + Send + 'static
+    {
+        let words = (mem::size_of::<Slot<F>>() + 7) / 8;
+        if mem::align_of::<Slot<F>>() > mem::align_of::<u64>() || words > mem.words
+            || mem.in_use.swap(true, Ordering::SeqCst)
+        {
+            return Self::new(func);
+        }
+        Self::write(mem.ptr as *mut u8, Memory::Handler(mem.clone()), func)
+    }
+
+    fn place<F>(func: F) -> Callback
+        where F: FnOnce(*const IoService, ErrCode) + Send + 'static
+    {
+        let words = (mem::size_of::<Slot<F>>() + BLOCK_WORDS * 8 - 1) / (BLOCK_WORDS * 8) * BLOCK_WORDS;
+        Self::write(allocate(words) as *mut u8, Memory::Recycled(words), func)
+    }
+
+    fn write<F>(ptr: *mut u8, mem: Memory, func: F) -> Callback
+        where F: FnOnce(*const IoService, ErrCode) + Send + 'static
+    {
+        unsafe { ptr::write(ptr as *mut Slot<F>, Slot { mem: mem, func: func }) };
+        Callback {
+            ptr: ptr,
+            call: call_slot::<F>,
+            drop: drop_slot::<F>,
+            cancel: Vec::new(),
+        }
+    }
+
+    // CancellationSignal に結びつける. 複数のシグナルに結びつけられる.
+    pub fn set_cancel(&mut self, state: Arc<CancelState>, id: usize) {
+        self.cancel.push((state, id));
+    }
+
+    pub fn cancel_states(&self) -> &[(Arc<CancelState>, usize)] {
+        &self.cancel
+    }
+
+    pub fn call(self, io: *const IoService, ec: ErrCode) {
+        let (ptr, call) = (self.ptr, self.call);
+        mem::forget(self);
+        unsafe { call(ptr, io, ec) }
+    }
+}
+
+unsafe impl Send for Callback {
+}
+
+impl Drop for Callback {
+    fn drop(&mut self) {
+        unsafe { (self.drop)(self.ptr) }
+    }
+}
+
+#[cfg(test)]
+use error::READY;
+
+#[test]
+fn test_callback_recycle() {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+
+    static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let io = &IoService::new();
+    let cb = Callback::new(|_, _| { COUNT.fetch_add(1, Ordering::SeqCst); });
+    let ptr = cb.ptr;
+    cb.call(io, READY);
+    assert_eq!(COUNT.load(Ordering::SeqCst), 1);
+
+    let cb = Callback::new(|_, _| { COUNT.fetch_add(1, Ordering::SeqCst); });
+    assert_eq!(cb.ptr, ptr);
+    drop(cb);
+    assert_eq!(COUNT.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_callback_drop() {
+    let data = Arc::new(0);
+    let data_ = data.clone();
+    let cb = Callback::new(move |_, _| { let _ = data_; });
+    assert_eq!(Arc::strong_count(&data), 2);
+    drop(cb);
+    assert_eq!(Arc::strong_count(&data), 1);
+}
+
+#[test]
+fn test_callback_after_tls_teardown() {
+    use std::thread;
+
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            // RECYCLER より先に初期化したので、RECYCLER が破棄された後に呼ばれる.
+            drop(Callback::new(|_, _| {}));
+        }
+    }
+
+    thread_local!(static GUARD: Guard = Guard);
+
+    thread::spawn(|| {
+        GUARD.with(|_| {});
+        drop(Callback::new(|_, _| {}));
+    }).join().unwrap();
+}
+
+#[test]
+fn test_callback_with_memory() {
+    let io = &IoService::new();
+    let mem = Arc::new(HandlerMemory::new(64));
+    let cb1 = Callback::with_memory(&mem, |_, _| {});
+    assert_eq!(cb1.ptr, mem.ptr as *mut u8);
+    assert!(mem.in_use());
+
+    let cb2 = Callback::with_memory(&mem, |_, _| {});
+    assert!(cb2.ptr != mem.ptr as *mut u8);
+
+    let mem_ = mem.clone();
+    cb1.call(io, READY);
+    assert!(!mem_.in_use());
+
+    let buf = [0u8; 128];
+    let cb3 = Callback::with_memory(&mem, move |_, _| { let _ = buf; });
+    assert!(cb3.ptr != mem.ptr as *mut u8);
+    drop(cb2);
+    drop(cb3);
+}
+
+#[cfg(test)]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(test)]
+use std::cell::Cell;
+
+// 有効にしたスレッドでの確保の回数を数えるアロケータ.
+#[cfg(test)]
+struct CountingAlloc;
+
+#[cfg(test)]
+thread_local!(static ALLOCATIONS: Cell<Option<usize>> = Cell::new(None));
+
+#[cfg(test)]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| if let Some(n) = count.get() {
+            count.set(Some(n + 1));
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+#[cfg(test)]
+fn count_allocations<F: FnOnce()>(func: F) -> usize {
+    ALLOCATIONS.with(|count| count.set(Some(0)));
+    func();
+    ALLOCATIONS.with(|count| count.replace(None)).unwrap()
+}
+
+#[test]
+fn test_post_without_allocation() {
+    use std::sync::atomic::AtomicUsize;
+
+    // ハンドラの中から次のハンドラを post し続ける.
+    fn chain(io: &IoService, count: Arc<AtomicUsize>) {
+        if count.fetch_sub(1, Ordering::SeqCst) > 1 {
+            io.post(move |io| chain(io, count));
+        }
+    }
+
+    let io = &IoService::new();
+    let count = Arc::new(AtomicUsize::new(0));
+    let run = |n| {
+        io.reset();
+        count.store(n, Ordering::SeqCst);
+        let count_ = count.clone();
+        io.post(move |io| chain(io, count_));
+        io.run();
+    };
+
+    // 実行キューと再利用するメモリが用意できた後は、post と実行でヒープを確保しない.
+    run(100);
+    let allocs = count_allocations(|| run(1000));
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+    assert_eq!(allocs, 0);
+}
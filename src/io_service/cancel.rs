@@ -0,0 +1,231 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use error::{ErrCode, ECANCELED};
+use super::{IoService, Callback, Handler};
+
+#[derive(Default)]
+struct CancelData {
+    id: usize,
+    cancelled: Option<ErrCode>,
+    registered: Option<(IoService, usize, bool)>,
+}
+
+#[derive(Default)]
+pub struct CancelState(Mutex<CancelData>);
+
+impl CancelState {
+    // リアクタに積まれた操作を登録する. 既にキャンセルされていればそのエラーを返す.
+    pub fn register(&self, id: usize, io: &IoService, token: usize, input: bool) -> Result<(), ErrCode> {
+        let mut data = self.0.lock().unwrap();
+        if data.id != id {
+            return Ok(());
+        }
+        if let Some(ec) = data.cancelled {
+            return Err(ec);
+        }
+        data.registered = Some((io.clone(), token, input));
+        Ok(())
+    }
+
+    // 結びついている操作を ec で完了させる.
+    pub fn cancel(this: &Arc<CancelState>, ec: ErrCode) {
+        let (id, registered) = {
+            let mut data = this.0.lock().unwrap();
+            if data.cancelled.is_some() {
+                return;
+            }
+            data.cancelled = Some(ec);
+            (data.id, data.registered.take())
+        };
+        if let Some((io, token, input)) = registered {
+            io.0.react.cancel_op(&io, token, input, this, id, ec);
+        }
+    }
+}
+
+/// The signal that cancels a single asynchronous operation.
+///
+/// Unlike `cancel()` of the sockets, which cancels all of the pending operations on the socket,
+/// the `CancellationSignal` cancels only the operation that was given the handler bound by `bind_cancellation`.
+/// The cancelled operation completes with `ECANCELED`.
+///
+/// # Examples
+/// ```
+/// use std::io;
+/// use std::sync::Arc;
+/// use asyncio::{IoService, Stream, CancellationSignal, bind_cancellation, wrap};
+/// use asyncio::local::{LocalStream, LocalStreamSocket, connect_pair};
+///
+/// let io = &IoService::new();
+/// let (soc, _peer): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+/// let soc = Arc::new(soc);
+/// let signal = CancellationSignal::new();
+///
+/// let mut buf = [0; 8];
+/// soc.async_read_some(&mut buf, bind_cancellation(wrap(|_: Arc<LocalStreamSocket>, res: io::Result<usize>| {
+///     assert!(res.is_err());  // ECANCELED
+/// }, &soc), &signal));
+/// soc.async_write_some(b"hello", wrap(|_: Arc<LocalStreamSocket>, res: io::Result<usize>| {
+///     assert_eq!(res.unwrap(), 5);
+/// }, &soc));
+///
+/// signal.emit();
+/// io.run();
+/// ```
+#[derive(Clone, Default)]
+pub struct CancellationSignal(Arc<CancelState>);
+
+impl CancellationSignal {
+    /// Returns a new `CancellationSignal`.
+    pub fn new() -> CancellationSignal {
+        CancellationSignal::default()
+    }
+
+    /// Cancels the operation which is bound to the signal.
+    ///
+    /// It has no effect if the operation has already completed.
+    pub fn emit(&self) {
+        CancelState::cancel(&self.0, ECANCELED)
+    }
+
+    // 新しい操作を結びつけて、その識別子を返す.
+    fn attach(&self) -> usize {
+        let mut data = (self.0).0.lock().unwrap();
+        data.id = data.id.wrapping_add(1);
+        data.cancelled = None;
+        data.registered = None;
+        data.id
+    }
+}
+
+/// The handler that is bound to a `CancellationSignal`.
+pub struct CancellationHandler<H> {
+    handler: H,
+    signal: CancellationSignal,
+    id: usize,
+}
+
+impl<H, R> Handler<R> for CancellationHandler<H>
+    where H: Handler<R>,
+{
+    type Output = H::Output;
+
+    fn callback(self, io: &IoService, res: io::Result<R>) {
+        self.handler.callback(io, res)
+    }
+
+    fn wrap<G>(self, callback: G) -> Callback
+        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
+    {
+        let CancellationHandler { handler, signal, id } = self;
+        let state = signal.0.clone();
+        let mut callback = handler.wrap(move |io, ec, handler| {
+            callback(io, ec, CancellationHandler {
+                handler: handler,
+                signal: signal,
+                id: id,
+            })
+        });
+        callback.set_cancel(state, id);
+        callback
+    }
+
+    type AsyncResult = H::AsyncResult;
+
+    fn async_result(&self) -> Self::AsyncResult {
+        self.handler.async_result()
+    }
+}
+
+/// Binds the handler to the `CancellationSignal`.
+///
+/// The signal is bound to only one operation at a time, and the previous binding is released.
+pub fn bind_cancellation<H>(handler: H, signal: &CancellationSignal) -> CancellationHandler<H> {
+    CancellationHandler {
+        handler: handler,
+        signal: signal.clone(),
+        id: signal.attach(),
+    }
+}
+
+#[cfg(test)]
+use std::os::unix::io::AsRawFd;
+#[cfg(test)]
+use super::{MockReactor, wrap};
+#[cfg(test)]
+use local::{LocalStream, LocalStreamSocket, connect_pair};
+#[cfg(test)]
+use stream::Stream;
+
+#[cfg(test)]
+fn read_result(res: &Arc<Mutex<Option<io::Result<usize>>>>) -> Box<FnMut(Arc<LocalStreamSocket>, io::Result<usize>) + Send> {
+    let res = res.clone();
+    Box::new(move |_, r| *res.lock().unwrap() = Some(r))
+}
+
+#[test]
+fn test_cancel_one_op() {
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let rx = Arc::new(rx);
+    let signal = CancellationSignal::new();
+    let res1 = Arc::new(Mutex::new(None));
+    let res2 = Arc::new(Mutex::new(None));
+
+    let mut buf1 = [0; 8];
+    let mut buf2 = [0; 8];
+    let mut f1 = read_result(&res1);
+    let mut f2 = read_result(&res2);
+    rx.async_read_some(&mut buf1, bind_cancellation(wrap(move |soc, r| f1(soc, r), &rx), &signal));
+    rx.async_read_some(&mut buf2, wrap(move |soc, r| f2(soc, r), &rx));
+    assert_eq!(io.stats().pending_input_ops, 2);
+
+    signal.emit();
+    assert_eq!(io.stats().pending_input_ops, 1);
+    io.poll();
+    assert!(res1.lock().unwrap().take().unwrap().is_err());
+    assert!(res2.lock().unwrap().is_none());
+
+    io.reset();
+    tx.write_some(b"hello").unwrap();
+    io.run();
+    assert_eq!(res2.lock().unwrap().take().unwrap().unwrap(), 5);
+}
+
+#[test]
+fn test_cancel_before_start() {
+    let io = &IoService::new();
+    let (rx, _tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let rx = Arc::new(rx);
+    let signal = CancellationSignal::new();
+    let res = Arc::new(Mutex::new(None));
+
+    let handler = bind_cancellation(wrap(read_result(&res), &rx), &signal);
+    signal.emit();
+    let mut buf = [0; 8];
+    rx.async_read_some(&mut buf, handler);
+    assert_eq!(io.stats().pending_input_ops, 0);
+    io.run();
+    assert!(res.lock().unwrap().take().unwrap().is_err());
+}
+
+#[test]
+fn test_cancel_in_flight() {
+    let mock = MockReactor::new();
+    let io = &IoService::with_reactor(mock.clone());
+    let (rx, _tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let rx = Arc::new(rx);
+    let signal = CancellationSignal::new();
+    let res = Arc::new(Mutex::new(None));
+
+    let mut buf = [0; 8];
+    rx.async_read_some(&mut buf, bind_cancellation(wrap(read_result(&res), &rx), &signal));
+    io.poll();
+
+    // 準備完了の通知を受けてからハンドラが実行されるまでの間にキャンセルする.
+    mock.readable(rx.as_raw_fd());
+    io.post(move |_| signal.emit());
+    io.poll();
+    assert!(res.lock().unwrap().take().unwrap().is_err());
+    assert_eq!(io.stats().pending_input_ops, 0);
+}
@@ -109,7 +109,7 @@ impl<R: Send + 'static> Handler<R> for CoroutineHandler<R> {
        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
     {
         debug_assert_eq!(self.0.data.is_ownered(), true);
-        Box::new(move |io: *const IoService, ec| {
+        Callback::new(move |io: *const IoService, ec| {
             let io = unsafe { &*io };
             let data = self.0.data.clone();
             debug_assert_eq!(data.is_ownered(), false);
@@ -0,0 +1,312 @@
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::future::Future;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{Ordering, AtomicBool};
+use error::{ErrCode, ECANCELED};
+use super::{IoService, IoServiceWork, Callback, CancelState, Handler, AsyncResult};
+
+struct FutureState<R> {
+    result: Option<io::Result<R>>,
+    waker: Option<Waker>,
+    done: bool,
+    dropped: bool,
+}
+
+struct FutureShared<R> {
+    state: Mutex<FutureState<R>>,
+    cancel: Arc<CancelState>,
+    running: Mutex<()>,
+}
+
+/// The handler that completes an `IoFuture`.
+pub struct UseFuture<R>(Arc<FutureShared<R>>);
+
+impl<R: Send + 'static> Handler<R> for UseFuture<R> {
+    type Output = IoFuture<R>;
+
+    fn callback(self, _: &IoService, res: io::Result<R>) {
+        let waker = {
+            let mut state = self.0.state.lock().unwrap();
+            state.result = Some(res);
+            state.done = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn wrap<G>(self, callback: G) -> Callback
+        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
+    {
+        let shared = self.0.clone();
+        let cancel = self.0.cancel.clone();
+        let mut callback = Callback::new(move |io: *const IoService, ec| {
+            // IoFuture が破棄された後はバッファに触れないように ECANCELED で完了させる.
+            let _running = shared.running.lock();
+            let ec = if shared.state.lock().unwrap().dropped { ECANCELED } else { ec };
+            callback(unsafe { &*io }, ec, self)
+        });
+        callback.set_cancel(cancel, 0);
+        callback
+    }
+
+    type AsyncResult = UseFutureAsyncResult<R>;
+
+    fn async_result(&self) -> Self::AsyncResult {
+        UseFutureAsyncResult(self.0.clone())
+    }
+}
+
+pub struct UseFutureAsyncResult<R>(Arc<FutureShared<R>>);
+
+impl<R> AsyncResult<IoFuture<R>> for UseFutureAsyncResult<R> {
+    fn get(self, _io: &IoService) -> IoFuture<R> {
+        IoFuture(self.0)
+    }
+}
+
+/// The `Future` of the result of an asynchronous operation.
+///
+/// The operation is started when the `IoFuture` is returned, and completes by the running `IoService`.
+/// Dropping a pending `IoFuture` cancels the operation, and waits for the handler if it is running
+/// in another thread, so the buffer given to the operation is no longer used after the drop.
+pub struct IoFuture<R>(Arc<FutureShared<R>>);
+
+impl<R> Future for IoFuture<R> {
+    type Output = io::Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut state = self.0.state.lock().unwrap();
+        match state.result.take() {
+            Some(res) => Poll::Ready(res),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+
+impl<R> Drop for IoFuture<R> {
+    fn drop(&mut self) {
+        {
+            let mut state = self.0.state.lock().unwrap();
+            if state.done {
+                return;
+            }
+            state.dropped = true;
+        }
+        CancelState::cancel(&self.0.cancel, ECANCELED);
+        // 別のスレッドで実行中のハンドラがバッファを使い終わるまで待つ.
+        let _running = self.0.running.lock();
+    }
+}
+
+/// Provides a `Future` handler to asynchronous operation.
+///
+/// The UseFuture has trait the `Handler`, that type of `Handler::Output` is `IoFuture<R>`,
+/// which is a `Future<Output = io::Result<R>>`.
+///
+/// In an `async` block of Rust 2018, the `IoFuture` is awaited by `.await`.
+///
+/// # Examples
+///
+/// ```
+/// use std::pin::Pin;
+/// use std::future::Future;
+/// use std::task::{Context, Poll};
+/// use std::time::Duration;
+/// use asyncio::{IoService, IoFuture, SteadyTimer, use_future};
+///
+/// struct Wait(IoFuture<()>);
+///
+/// impl Future for Wait {
+///     type Output = ();
+///
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+///         Pin::new(&mut self.0).poll(cx).map(|res| res.unwrap())
+///     }
+/// }
+///
+/// let io = &IoService::new();
+/// let timer = SteadyTimer::new(io);
+/// IoService::spawn_future(io, Wait(timer.async_wait_for(Duration::from_millis(1), use_future())));
+/// io.run();
+/// ```
+pub fn use_future<R>() -> UseFuture<R> {
+    UseFuture(Arc::new(FutureShared {
+        state: Mutex::new(FutureState {
+            result: None,
+            waker: None,
+            done: false,
+            dropped: false,
+        }),
+        cancel: Arc::new(CancelState::default()),
+        running: Mutex::new(()),
+    }))
+}
+
+struct FutureTask {
+    io: IoService,
+    future: Mutex<Option<Pin<Box<Future<Output = ()> + Send>>>>,
+    work: Mutex<Option<IoServiceWork>>,
+    scheduled: AtomicBool,
+}
+
+// 起こされたら IoService に post して、実行中のスレッドで poll し直す.
+fn schedule(task: Arc<FutureTask>) {
+    if !task.scheduled.swap(true, Ordering::SeqCst) {
+        let io = task.io.clone();
+        io.post(move |_| run_task(task));
+    }
+}
+
+fn run_task(task: Arc<FutureTask>) {
+    task.scheduled.store(false, Ordering::SeqCst);
+    let waker = new_waker(task.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = task.future.lock().unwrap();
+    let ready = match future.as_mut() {
+        Some(fut) => fut.as_mut().poll(&mut cx).is_ready(),
+        None => false,
+    };
+    if ready {
+        *future = None;
+        // 完了したので IoService の停止を妨げない.
+        task.work.lock().unwrap().take();
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(waker_clone, waker_wake, waker_wake_by_ref, waker_drop);
+
+fn new_waker(task: Arc<FutureTask>) -> Waker {
+    unsafe { Waker::from_raw(RawWaker::new(Arc::into_raw(task) as *const (), &VTABLE)) }
+}
+
+unsafe fn waker_clone(ptr: *const ()) -> RawWaker {
+    let task = Arc::from_raw(ptr as *const FutureTask);
+    mem::forget(task.clone());
+    RawWaker::new(Arc::into_raw(task) as *const (), &VTABLE)
+}
+
+unsafe fn waker_wake(ptr: *const ()) {
+    schedule(Arc::from_raw(ptr as *const FutureTask))
+}
+
+unsafe fn waker_wake_by_ref(ptr: *const ()) {
+    let task = Arc::from_raw(ptr as *const FutureTask);
+    schedule(task.clone());
+    mem::forget(task);
+}
+
+unsafe fn waker_drop(ptr: *const ()) {
+    drop(Arc::from_raw(ptr as *const FutureTask))
+}
+
+pub fn spawn_future<F>(io: &IoService, future: F)
+    where F: Future<Output = ()> + Send + 'static
+{
+    schedule(Arc::new(FutureTask {
+        io: io.clone(),
+        future: Mutex::new(Some(Box::pin(future))),
+        work: Mutex::new(Some(IoService::work(io))),
+        scheduled: AtomicBool::new(false),
+    }))
+}
+
+#[cfg(test)]
+use std::time::Duration;
+#[cfg(test)]
+use std::thread;
+#[cfg(test)]
+use waitable_timer::SteadyTimer;
+#[cfg(test)]
+use local::{LocalStream, LocalStreamSocket, connect_pair};
+#[cfg(test)]
+use stream::Stream;
+
+#[cfg(test)]
+struct Sequence<R>(Vec<IoFuture<R>>, Arc<Mutex<Vec<io::Result<R>>>>);
+
+#[cfg(test)]
+impl<R> Future for Sequence<R> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        while let Some(mut fut) = this.0.pop() {
+            match Pin::new(&mut fut).poll(cx) {
+                Poll::Ready(res) => this.1.lock().unwrap().push(res),
+                Poll::Pending => {
+                    this.0.push(fut);
+                    return Poll::Pending;
+                },
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+#[test]
+fn test_use_future() {
+    let io = &IoService::new();
+    let timer = SteadyTimer::new(io);
+    let fut = timer.async_wait_for(Duration::from_millis(1), use_future());
+    let res = Arc::new(Mutex::new(Vec::new()));
+    spawn_future(io, Sequence(vec![fut], res.clone()));
+    assert!(res.lock().unwrap().is_empty());
+    io.run();
+    assert_eq!(res.lock().unwrap().len(), 1);
+    assert!(res.lock().unwrap()[0].is_ok());
+}
+
+#[test]
+fn test_use_future_cancel() {
+    let io = &IoService::new();
+    let timer = SteadyTimer::new(io);
+    let fut = timer.async_wait_for(Duration::new(60, 0), use_future());
+    let res = Arc::new(Mutex::new(Vec::new()));
+    spawn_future(io, Sequence(vec![fut], res.clone()));
+    io.poll();
+    io.reset();
+    timer.cancel();
+    io.run();
+    assert!(res.lock().unwrap()[0].is_err());
+}
+
+#[test]
+fn test_use_future_drop() {
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let mut buf = [0; 8];
+    let fut = rx.async_read_some(&mut buf, use_future());
+    assert_eq!(io.stats().pending_input_ops, 1);
+    drop(fut);
+    assert_eq!(io.stats().pending_input_ops, 0);
+    tx.write_some(b"hello").unwrap();
+    io.run();
+    assert_eq!(buf, [0; 8]);
+    io.reset();
+    assert_eq!(rx.read_some(&mut buf).unwrap(), 5);
+}
+
+#[test]
+fn test_spawn_future_work() {
+    let io = &IoService::new();
+    let handler = use_future();
+    let fut = handler.async_result().get(io);
+    let res = Arc::new(Mutex::new(Vec::new()));
+    spawn_future(io, Sequence(vec![fut], res.clone()));
+    let io_ = io.clone();
+    let thrd = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        handler.callback(&io_, Ok(()));
+    });
+    io.run();
+    assert_eq!(res.lock().unwrap().len(), 1);
+    thrd.join().unwrap();
+}
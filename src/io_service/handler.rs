@@ -2,7 +2,7 @@ use std::io;
 use std::sync::Arc;
 use std::marker::PhantomData;
 use error::ErrCode;
-use super::{IoObject, IoService, Callback};
+use super::{IoObject, IoService, Callback, HandlerMemory};
 
 pub trait Handler<R> : Sized + Send + 'static {
     type Output;
@@ -35,9 +35,20 @@ impl AsyncResult<()> for NoAsyncResult {
 pub struct ArcHandler<T, F, R> {
     data: Arc<T>,
     handler: F,
+    mem: Option<Arc<HandlerMemory>>,
     _marker: PhantomData<R>,
 }
 
+impl<T, F, R> ArcHandler<T, F, R> {
+    /// Stores the completion handler in the given `HandlerMemory` instead of allocating it.
+    pub fn with_memory(self, mem: &Arc<HandlerMemory>) -> Self {
+        ArcHandler {
+            mem: Some(mem.clone()),
+            ..self
+        }
+    }
+}
+
 impl<T, F, R> Handler<R> for ArcHandler<T, F, R>
     where T: IoObject + Send + Sync + 'static,
           F: FnOnce(Arc<T>, io::Result<R>) + Send + 'static,
@@ -46,17 +57,22 @@ impl<T, F, R> Handler<R> for ArcHandler<T, F, R>
     type Output = ();
 
     fn callback(self, _: &IoService, res: io::Result<R>) {
-        let ArcHandler { data, handler, _marker } = self;
+        let ArcHandler { data, handler, mem: _, _marker } = self;
         handler(data, res)
     }
 
     fn wrap<G>(self, callback: G) -> Callback
         where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
     {
-        Box::new(move |io: *const IoService, ec| {
-            callback(unsafe { &*io }, ec, self)
-        })
+        match self.mem.clone() {
+            Some(mem) => Callback::with_memory(&mem, move |io: *const IoService, ec| {
+                callback(unsafe { &*io }, ec, self)
+            }),
+            None => Callback::new(move |io: *const IoService, ec| {
+                callback(unsafe { &*io }, ec, self)
+            }),
         }
+    }
 
     type AsyncResult = NoAsyncResult;
 
@@ -93,6 +109,7 @@ pub fn wrap<T, F, R>(handler: F, data: &Arc<T>) -> ArcHandler<T, F, R>
     ArcHandler {
         data: data.clone(),
         handler: handler,
+        mem: None,
         _marker: PhantomData,
     }
 }
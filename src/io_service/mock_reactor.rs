@@ -0,0 +1,211 @@
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use super::{Reactor, ReactorEvent};
+
+#[derive(Default)]
+struct MockData {
+    registered_entry: HashMap<RawFd, usize>,
+    interests: HashMap<RawFd, (bool, bool)>,
+    events: Vec<ReactorEvent>,
+    polls: usize,
+}
+
+/// The deterministic `Reactor` backend for tests.
+///
+/// The `MockReactor` never waits and never reports any events by itself.
+/// The readiness and the errors are injected by the tests, and are reported at the next poll
+/// whether or not the file descriptor is actually ready (i.e. spurious wakeups).
+///
+/// # Examples
+/// ```
+/// use std::io;
+/// use std::sync::Arc;
+/// use std::os::unix::io::AsRawFd;
+/// use asyncio::{IoService, MockReactor, Stream, wrap};
+/// use asyncio::local::{LocalStream, LocalStreamSocket, connect_pair};
+///
+/// let mock = MockReactor::new();
+/// let io = &IoService::with_reactor(mock.clone());
+/// let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+/// let rx = Arc::new(rx);
+///
+/// let mut buf = [0; 8];
+/// rx.async_read_some(&mut buf, wrap(|_: Arc<LocalStreamSocket>, res: io::Result<usize>| {
+///     assert_eq!(res.unwrap(), 5);
+/// }, &rx));
+/// io.poll();
+/// assert_eq!(io.stats().pending_input_ops, 1);
+///
+/// tx.write_some(b"hello").unwrap();
+/// mock.readable(rx.as_raw_fd());
+/// io.poll();
+/// assert_eq!(io.stats().pending_input_ops, 0);
+/// ```
+#[derive(Clone, Default)]
+pub struct MockReactor(Arc<Mutex<MockData>>);
+
+impl MockReactor {
+    /// Returns a new `MockReactor`.
+    pub fn new() -> MockReactor {
+        MockReactor::default()
+    }
+
+    fn inject(&self, fd: RawFd, readable: bool, writable: bool, error: bool) {
+        let mut mock = self.0.lock().unwrap();
+        if let Some(&token) = mock.registered_entry.get(&fd) {
+            mock.events.push(ReactorEvent {
+                token: token,
+                readable: readable,
+                writable: writable,
+                error: error,
+                result: None,
+            });
+        }
+    }
+
+    /// Injects the readiness to read.
+    pub fn readable(&self, fd: RawFd) {
+        self.inject(fd, true, false, false)
+    }
+
+    /// Injects the readiness to write.
+    pub fn writable(&self, fd: RawFd) {
+        self.inject(fd, false, true, false)
+    }
+
+    /// Injects an error or a hangup (e.g. `EPOLLERR` or `EPOLLHUP`).
+    pub fn error(&self, fd: RawFd) {
+        self.inject(fd, false, false, true)
+    }
+
+    /// Returns true if the file descriptor is registered.
+    pub fn is_registered(&self, fd: RawFd) -> bool {
+        self.0.lock().unwrap().registered_entry.contains_key(&fd)
+    }
+
+    /// Returns the last interests `(input, output)` of the file descriptor.
+    pub fn interest_of(&self, fd: RawFd) -> (bool, bool) {
+        self.0.lock().unwrap().interests.get(&fd).cloned().unwrap_or((false, false))
+    }
+
+    /// Returns the number of times the reactor was polled.
+    pub fn polls(&self) -> usize {
+        self.0.lock().unwrap().polls
+    }
+}
+
+impl Reactor for MockReactor {
+    fn register(&self, fd: RawFd, token: usize, _intr: bool) {
+        let mut mock = self.0.lock().unwrap();
+        mock.registered_entry.insert(fd, token);
+    }
+
+    fn deregister(&self, fd: RawFd, _token: usize) {
+        let mut mock = self.0.lock().unwrap();
+        mock.registered_entry.remove(&fd);
+        mock.interests.remove(&fd);
+    }
+
+    fn interest(&self, fd: RawFd, _token: usize, input: bool, output: bool) {
+        let mut mock = self.0.lock().unwrap();
+        mock.interests.insert(fd, (input, output));
+    }
+
+    fn poll(&self, _timeout: Option<i32>, events: &mut Vec<ReactorEvent>) {
+        let mut mock = self.0.lock().unwrap();
+        mock.polls += 1;
+        events.extend(mock.events.drain(..));
+    }
+}
+
+#[cfg(test)]
+use std::io;
+#[cfg(test)]
+use std::os::unix::io::AsRawFd;
+#[cfg(test)]
+use io_service::{IoService, wrap};
+#[cfg(test)]
+use local::{LocalStream, LocalStreamSocket, connect_pair};
+#[cfg(test)]
+use stream::Stream;
+
+#[cfg(test)]
+fn mock_pair(mock: &MockReactor) -> (IoService, Arc<LocalStreamSocket>, LocalStreamSocket) {
+    let io = IoService::with_reactor(mock.clone());
+    let (rx, tx) = connect_pair(&io, LocalStream).unwrap();
+    (io, Arc::new(rx), tx)
+}
+
+#[cfg(test)]
+fn async_read_result(rx: &Arc<LocalStreamSocket>, buf: &mut [u8]) -> Arc<Mutex<Option<io::Result<usize>>>> {
+    let res = Arc::new(Mutex::new(None));
+    let res_ = res.clone();
+    rx.async_read_some(buf, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        *res_.lock().unwrap() = Some(r);
+    }, rx));
+    res
+}
+
+#[test]
+fn test_mock_spurious_readable() {
+    let mock = MockReactor::new();
+    let (io, rx, _tx) = mock_pair(&mock);
+    let mut buf = [0; 8];
+    let res = async_read_result(&rx, &mut buf);
+    assert_eq!(mock.interest_of(rx.as_raw_fd()), (true, false));
+
+    mock.readable(rx.as_raw_fd());
+    io.poll();
+    assert!(res.lock().unwrap().is_none());
+    assert_eq!(io.stats().pending_input_ops, 1);
+    assert_eq!(mock.interest_of(rx.as_raw_fd()), (true, false));
+}
+
+#[test]
+fn test_mock_ready_before_op() {
+    let mock = MockReactor::new();
+    let (io, rx, tx) = mock_pair(&mock);
+    mock.readable(rx.as_raw_fd());
+    io.poll();
+    io.reset();
+
+    tx.write_some(b"hello").unwrap();
+    let mut buf = [0; 8];
+    let res = async_read_result(&rx, &mut buf);
+    assert_eq!(io.stats().pending_input_ops, 0);
+    io.poll();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 5);
+}
+
+#[test]
+fn test_mock_error() {
+    let mock = MockReactor::new();
+    let (io, rx, tx) = mock_pair(&mock);
+    let mut buf = [0; 8];
+    let res = async_read_result(&rx, &mut buf);
+    drop(tx);
+
+    mock.error(rx.as_raw_fd());
+    io.poll();
+    assert!(res.lock().unwrap().take().unwrap().is_err());
+    assert_eq!(io.stats().pending_input_ops, 0);
+}
+
+#[test]
+fn test_mock_cancel_in_flight() {
+    let mock = MockReactor::new();
+    let (io, rx, _tx) = mock_pair(&mock);
+    let mut buf = [0; 8];
+    let res = async_read_result(&rx, &mut buf);
+    io.poll();
+
+    // 準備完了の通知を受けてからハンドラが実行されるまでの間にキャンセルする.
+    mock.readable(rx.as_raw_fd());
+    let rx_ = rx.clone();
+    io.post(move |_| rx_.cancel());
+    io.poll();
+    assert!(res.lock().unwrap().take().unwrap().is_err());
+    assert_eq!(io.stats().pending_input_ops, 0);
+    assert_eq!(mock.interest_of(rx.as_raw_fd()), (false, false));
+}
@@ -1,21 +1,68 @@
+use std::any::Any;
+use std::future::Future;
 use std::sync::Arc;
-use std::boxed::FnBox;
-use error::ErrCode;
+use std::time::{Duration, Instant};
 pub use std::os::unix::io::{RawFd, AsRawFd};
 
-pub type Callback = Box<FnBox(*const IoService, ErrCode) + Send + 'static>;
+mod callback;
+pub use self::callback::{Callback, HandlerMemory};
+
+mod cancel;
+pub use self::cancel::{CancelState, CancellationSignal, CancellationHandler, bind_cancellation};
 
 //---------
 // Reactor
 
+mod reactor;
+pub use self::reactor::{Reactor, ReactorEvent, ReactorOp, ReactorCore, IoActor, IntrActor};
+
 #[cfg(all(feature = "epoll", target_os = "linux"))] mod epoll_reactor;
-#[cfg(all(feature = "epoll", target_os = "linux"))] pub use self::epoll_reactor::{Reactor, IoActor, IntrActor};
+#[cfg(all(feature = "epoll", target_os = "linux"))] pub use self::epoll_reactor::EpollReactor;
+
+#[cfg(all(feature = "io_uring", target_os = "linux"))] mod uring_reactor;
+#[cfg(all(feature = "io_uring", target_os = "linux"))] pub use self::uring_reactor::UringReactor;
 
 #[cfg(all(feature = "kqueue", target_os = "macos"))] mod kqueue_reactor;
-#[cfg(all(feature = "kqueue", target_os = "macos"))] pub use self::kqueue_reactor::{Reactor, IoActor, IntrActor};
+#[cfg(all(feature = "kqueue", target_os = "macos"))] pub use self::kqueue_reactor::KqueueReactor;
+
+mod poll_reactor;
+pub use self::poll_reactor::PollReactor;
+
+mod mock_reactor;
+pub use self::mock_reactor::MockReactor;
+
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+fn default_reactor() -> Box<Reactor> {
+    match UringReactor::try_new() {
+        Ok(react) => Box::new(react),
+        Err(_) => fallback_reactor(),
+    }
+}
+
+#[cfg(all(feature = "io_uring", feature = "epoll", target_os = "linux"))]
+fn fallback_reactor() -> Box<Reactor> {
+    Box::new(EpollReactor::new())
+}
 
-#[cfg(not(any(all(feature = "epoll", target_os = "linux"), all(feature = "kqueue", target_os = "macos"))))] mod null_reactor;
-#[cfg(not(any(all(feature = "epoll", target_os = "linux"), all(feature = "kqueue", target_os = "macos"))))] pub use self::null_reactor::{Reactor, IntrActor, IoActor};
+#[cfg(all(feature = "io_uring", not(feature = "epoll"), target_os = "linux"))]
+fn fallback_reactor() -> Box<Reactor> {
+    Box::new(PollReactor::new())
+}
+
+#[cfg(all(feature = "epoll", not(feature = "io_uring"), target_os = "linux"))]
+fn default_reactor() -> Box<Reactor> {
+    Box::new(EpollReactor::new())
+}
+
+#[cfg(all(feature = "kqueue", target_os = "macos"))]
+fn default_reactor() -> Box<Reactor> {
+    Box::new(KqueueReactor::new())
+}
+
+#[cfg(not(any(all(any(feature = "epoll", feature = "io_uring"), target_os = "linux"), all(feature = "kqueue", target_os = "macos"))))]
+fn default_reactor() -> Box<Reactor> {
+    Box::new(PollReactor::new())
+}
 
 //---------
 // control
@@ -32,6 +79,9 @@ pub type Callback = Box<FnBox(*const IoService, ErrCode) + Send + 'static>;
 mod thread_info;
 pub use self::thread_info::{CallStack, ThreadInfo};
 
+mod stats;
+pub use self::stats::{IoServiceStats, LatencyHistogram, LATENCY_BUCKETS};
+
 mod task_io_service;
 use self::task_io_service::IoServiceImpl;
 
@@ -65,7 +115,23 @@ impl IoService {
     /// let io = IoService::new();
     /// ```
     pub fn new() -> IoService {
-        IoService(Arc::new(IoServiceImpl::new()))
+        IoService(Arc::new(IoServiceImpl::new(default_reactor())))
+    }
+
+    /// Returns a new `IoService` with the given `Reactor` backend.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::{IoService, PollReactor};
+    ///
+    /// let io = IoService::with_reactor(PollReactor::new());
+    /// io.post(|_| {});
+    /// assert_eq!(io.run(), 1);
+    /// ```
+    pub fn with_reactor<R>(react: R) -> IoService
+        where R: Reactor,
+    {
+        IoService(Arc::new(IoServiceImpl::new(Box::new(react))))
     }
 
     /// Requests a process to invoke the given handler.
@@ -115,6 +181,33 @@ impl IoService {
         self.0.post(func);
     }
 
+    /// Requests a process to invoke the given handler as a continuation of the current handler.
+    ///
+    /// If the current thread is running the `IoService`, the handler is queued to the thread's own queue
+    /// without waking up other threads. Otherwise it is the same as `post`.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    /// use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
+    ///
+    /// static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+    ///
+    /// let io = IoService::new();
+    /// io.post(|io| {
+    ///     io.defer(|_| { COUNT.fetch_add(1, Ordering::SeqCst); });
+    ///     COUNT.fetch_add(1, Ordering::SeqCst);
+    /// });
+    /// io.run();
+    ///
+    /// assert_eq!(COUNT.load(Ordering::Relaxed), 2);
+    /// ```
+    pub fn defer<F>(&self, func: F)
+        where F: FnOnce(&IoService) + Send + 'static
+    {
+        self.0.defer(func);
+    }
+
     /// Resets a stopped `IoService`.
     ///
     /// # Examples
@@ -134,6 +227,8 @@ impl IoService {
 
     /// Runs all given handlers.
     ///
+    /// Returns the number of handlers that were executed.
+    ///
     /// # Examples
     /// ```
     /// use asyncio::IoService;
@@ -141,10 +236,105 @@ impl IoService {
     /// let io = IoService::new();
     /// io.run();
     /// ```
-    pub fn run(&self) {
+    pub fn run(&self) -> usize {
         self.0.run(self)
     }
 
+    /// Runs at most one handler, blocking until a handler is ready or the `IoService` has been stopped.
+    ///
+    /// Returns the number of handlers that were executed.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    ///
+    /// let io = IoService::new();
+    /// io.post(|_| {});
+    /// io.post(|_| {});
+    /// assert_eq!(io.run_one(), 1);
+    /// assert_eq!(io.run_one(), 1);
+    /// assert_eq!(io.run_one(), 0);
+    /// ```
+    pub fn run_one(&self) -> usize {
+        self.0.run_one(self)
+    }
+
+    /// Runs handlers until the given duration has elapsed.
+    ///
+    /// Unlike `stop()`, the `IoService` is not stopped when the duration has elapsed.
+    /// Returns the number of handlers that were executed.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    /// use std::time::Duration;
+    ///
+    /// let io = IoService::new();
+    /// let _work = IoService::work(&io);
+    /// io.post(|_| {});
+    /// assert_eq!(io.run_for(Duration::new(0, 1000000)), 1);
+    /// assert_eq!(io.stopped(), false);
+    /// ```
+    pub fn run_for(&self, duration: Duration) -> usize {
+        self.run_until(Instant::now() + duration)
+    }
+
+    /// Runs handlers until the given time point has been reached.
+    ///
+    /// Unlike `stop()`, the `IoService` is not stopped when the time point has been reached.
+    /// Returns the number of handlers that were executed.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let io = IoService::new();
+    /// let _work = IoService::work(&io);
+    /// io.post(|_| {});
+    /// assert_eq!(io.run_until(Instant::now() + Duration::new(0, 1000000)), 1);
+    /// assert_eq!(io.stopped(), false);
+    /// ```
+    pub fn run_until(&self, expiry: Instant) -> usize {
+        self.0.run_until(self, expiry)
+    }
+
+    /// Runs all handlers that are ready to run, without blocking.
+    ///
+    /// Returns the number of handlers that were executed.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    ///
+    /// let io = IoService::new();
+    /// io.post(|_| {});
+    /// io.post(|_| {});
+    /// assert_eq!(io.poll(), 2);
+    /// ```
+    pub fn poll(&self) -> usize {
+        self.0.poll(self)
+    }
+
+    /// Runs at most one handler that is ready to run, without blocking.
+    ///
+    /// Returns the number of handlers that were executed.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    ///
+    /// let io = IoService::new();
+    /// io.post(|_| {});
+    /// io.post(|_| {});
+    /// assert_eq!(io.poll_one(), 1);
+    /// assert_eq!(io.poll_one(), 1);
+    /// assert_eq!(io.poll_one(), 0);
+    /// ```
+    pub fn poll_one(&self) -> usize {
+        self.0.poll_one(self)
+    }
+
     /// Sets a stop request and cancel all of the waiting event in an `IoService`.
     ///
     /// # Examples
@@ -158,6 +348,80 @@ impl IoService {
         self.0.stop()
     }
 
+    /// Sets a graceful stop request.
+    ///
+    /// The pending accept operations are canceled immediately, and new ones are canceled too.
+    /// The `IoService` keeps running the queued handlers and the pending write operations
+    /// until they are finished or the `timeout` elapses,
+    /// and then cancels the remaining operations as `stop()`.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    /// use std::time::Duration;
+    ///
+    /// let io = IoService::new();
+    /// let work = IoService::work(&io);
+    /// io.post(|io| io.graceful_stop(Duration::from_secs(1)));
+    /// io.run();
+    /// assert_eq!(io.stopped(), true);
+    /// ```
+    pub fn graceful_stop(&self, timeout: Duration) {
+        self.0.graceful_stop(self, timeout)
+    }
+
+    /// Returns true if this is in a graceful stop.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    /// use std::time::Duration;
+    ///
+    /// let io = IoService::new();
+    /// assert_eq!(io.draining(), false);
+    /// io.graceful_stop(Duration::from_secs(1));
+    /// assert_eq!(io.draining(), true);
+    /// ```
+    pub fn draining(&self) -> bool {
+        self.0.draining()
+    }
+
+    /// Returns a snapshot of the runtime statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::IoService;
+    ///
+    /// let io = IoService::new();
+    /// let stats = io.stats();
+    /// assert_eq!(stats.queued_handlers, 0);
+    /// assert_eq!(stats.pending_timers, 0);
+    /// ```
+    pub fn stats(&self) -> IoServiceStats {
+        self.0.stats()
+    }
+
+    /// Sets a policy of the panic in a handler.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::{IoService, PanicPolicy};
+    /// use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
+    ///
+    /// static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+    ///
+    /// let io = IoService::new();
+    /// io.set_panic_policy(PanicPolicy::catch(|_, _| { COUNT.fetch_add(1, Ordering::SeqCst); }));
+    /// io.post(|_| panic!("bad handler"));
+    /// io.post(|_| { COUNT.fetch_add(1, Ordering::SeqCst); });
+    /// io.run();
+    ///
+    /// assert_eq!(COUNT.load(Ordering::Relaxed), 2);
+    /// ```
+    pub fn set_panic_policy(&self, policy: PanicPolicy) {
+        self.0.set_panic_policy(policy)
+    }
+
     /// Returns true if this has been stopped.
     ///
     /// # Examples
@@ -229,6 +493,58 @@ impl IoService {
     {
         spawn(io, func);
     }
+
+    /// Starts a new `Future` that is driven by the `IoService`.
+    ///
+    /// The future is polled in the threads that run the `IoService`, and is polled again
+    /// by posting to the `IoService` when it is woken up.
+    /// The `IoService` does not stop running until the future completes.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io;
+    /// use std::pin::Pin;
+    /// use std::future::Future;
+    /// use std::sync::{Arc, Mutex};
+    /// use std::task::{Context, Poll};
+    /// use asyncio::{IoService, IoFuture, Stream, use_future};
+    /// use asyncio::local::{LocalStream, LocalStreamSocket, connect_pair};
+    ///
+    /// struct Store(IoFuture<usize>, Arc<Mutex<Option<io::Result<usize>>>>);
+    ///
+    /// impl Future for Store {
+    ///     type Output = ();
+    ///
+    ///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+    ///         match Pin::new(&mut self.0).poll(cx) {
+    ///             Poll::Ready(res) => {
+    ///                 *self.1.lock().unwrap() = Some(res);
+    ///                 Poll::Ready(())
+    ///             },
+    ///             Poll::Pending => Poll::Pending,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let io = &IoService::new();
+    /// let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    /// let read = Arc::new(Mutex::new(None));
+    /// let written = Arc::new(Mutex::new(None));
+    ///
+    /// let mut buf = [0; 8];
+    /// IoService::spawn_future(io, Store(rx.async_read_some(&mut buf, use_future()), read.clone()));
+    /// IoService::spawn_future(io, Store(tx.async_write_some(b"hello", use_future()), written.clone()));
+    /// io.run();
+    ///
+    /// assert_eq!(written.lock().unwrap().take().unwrap().unwrap(), 5);
+    /// let len = read.lock().unwrap().take().unwrap().unwrap();
+    /// assert_eq!(&buf[..len], b"hello");
+    /// ```
+    pub fn spawn_future<F>(io: &IoService, future: F)
+        where F: Future<Output = ()> + Send + 'static,
+    {
+        spawn_future(io, future)
+    }
 }
 
 unsafe impl IoObject for IoService {
@@ -237,6 +553,30 @@ unsafe impl IoObject for IoService {
     }
 }
 
+/// The policy of the panic in a handler that is run by `IoService`.
+#[derive(Clone)]
+pub enum PanicPolicy {
+    /// Propagates the panic out of `run()`, after the `IoService` has been cleaned up.
+    ///
+    /// This is the default policy.
+    Propagate,
+
+    /// Aborts the process.
+    Abort,
+
+    /// Catches the panic and reports it to the hook, then continues to run handlers.
+    Catch(Arc<Fn(&IoService, Box<Any + Send>) + Send + Sync>),
+}
+
+impl PanicPolicy {
+    /// Returns a `PanicPolicy::Catch` of the given hook.
+    pub fn catch<F>(hook: F) -> PanicPolicy
+        where F: Fn(&IoService, Box<Any + Send>) + Send + Sync + 'static
+    {
+        PanicPolicy::Catch(Arc::new(hook))
+    }
+}
+
 /// The class to delaying until the stop of `IoService` is dropped.
 ///
 /// # Examples
@@ -322,6 +662,18 @@ pub use self::handler::{Handler, AsyncResult, NoAsyncResult, wrap};
 mod strand;
 pub use self::strand::{Strand, StrandImmutable, StrandHandler, strand_clone, strand_new};
 
+mod pool;
+pub use self::pool::IoServicePool;
+
+mod future;
+pub use self::future::{UseFuture, IoFuture, use_future, spawn_future};
+
+mod promise;
+pub use self::promise::{UsePromise, Promise, use_promise};
+
+mod timeout;
+pub use self::timeout::{TimeoutHandler, with_timeout};
+
 #[cfg(feature = "context")] mod coroutine;
 #[cfg(feature = "context")] pub use self::coroutine::{Coroutine, spawn};
 
@@ -356,3 +708,259 @@ fn test_multithread_working() {
 
     assert_eq!(COUNT.load(Ordering::Relaxed), 100);
 }
+
+#[test]
+fn test_poll_not_blocking() {
+    use std::time::Duration;
+    use std::sync::Arc;
+    use std::io;
+    use waitable_timer::SteadyTimer;
+
+    let io = &IoService::new();
+    let timer = Arc::new(SteadyTimer::new(io));
+    timer.async_wait_for(Duration::new(0, 10000000), wrap(|_: Arc<SteadyTimer>, res: io::Result<()>| assert!(res.is_ok()), &timer));
+    assert_eq!(io.poll(), 0);
+    assert_eq!(io.stopped(), false);
+    assert_eq!(io.run(), 1);
+}
+
+#[test]
+fn test_run_for_multithread() {
+    use std::thread;
+    use std::time::Duration;
+
+    let io = &IoService::new();
+    let _work = IoService::work(io);
+
+    let mut thrds = Vec::new();
+    for _ in 0..4 {
+        let io = io.clone();
+        thrds.push(thread::spawn(move || io.run_for(Duration::new(0, 10000000))));
+    }
+    for thrd in thrds {
+        thrd.join().unwrap();
+    }
+    assert_eq!(io.stopped(), false);
+}
+
+#[test]
+fn test_panic_propagate() {
+    use std::panic;
+    use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
+
+    static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let io = &IoService::new();
+    io.post(|_| panic!("bad handler"));
+    io.post(|_| { COUNT.fetch_add(1, Ordering::SeqCst); });
+    assert!(panic::catch_unwind(|| io.run()).is_err());
+    assert_eq!(io.stats().running_threads, 0);
+    io.run();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_panic_in_strand() {
+    use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
+
+    static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let io = &IoService::new();
+    io.set_panic_policy(PanicPolicy::catch(|_, _| {}));
+    let st = IoService::strand(io, 0);
+    st.post(|_| panic!("bad handler"));
+    st.post(|_| { COUNT.fetch_add(1, Ordering::SeqCst); });
+    io.run();
+    st.post(|_| { COUNT.fetch_add(1, Ordering::SeqCst); });
+    io.reset();
+    io.run();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn test_defer_same_thread() {
+    use std::thread;
+    use std::sync::{Arc, Mutex};
+    use thread_id;
+
+    let io = &IoService::new();
+    let _work = IoService::work(io);
+    let res = Arc::new(Mutex::new(Vec::new()));
+    let res_ = res.clone();
+    io.post(move |io| {
+        let tid = thread_id::get();
+        for i in 0..10 {
+            let res = res_.clone();
+            io.defer(move |_| {
+                assert_eq!(thread_id::get(), tid);
+                res.lock().unwrap().push(i);
+            });
+        }
+        io.defer(|io| io.stop());
+    });
+
+    let mut thrds = Vec::new();
+    for _ in 0..4 {
+        let io = io.clone();
+        thrds.push(thread::spawn(move || io.run()));
+    }
+    for thrd in thrds {
+        thrd.join().unwrap();
+    }
+    assert_eq!(*res.lock().unwrap(), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_defer_counted() {
+    let io = &IoService::new();
+    io.post(|io| {
+        io.defer(|_| {});
+        io.defer(|_| {});
+        assert_eq!(io.stats().queued_handlers, 2);
+    });
+    assert_eq!(io.run(), 3);
+    assert_eq!(io.stats().queued_handlers, 0);
+}
+
+#[test]
+fn test_defer_other_service() {
+    use std::sync::Arc;
+    use std::sync::atomic::{Ordering, AtomicBool};
+
+    let io1 = &IoService::new();
+    let io2 = IoService::new();
+    let called = Arc::new(AtomicBool::new(false));
+    let called_ = called.clone();
+    let io2_ = io2.clone();
+    io1.post(move |_| {
+        let ptr = &*io2_.0 as *const IoServiceImpl as usize;
+        io2_.defer(move |io| {
+            assert_eq!(&*io.0 as *const IoServiceImpl as usize, ptr);
+            called_.store(true, Ordering::SeqCst);
+        });
+    });
+    assert_eq!(io1.run(), 1);
+    assert!(!called.load(Ordering::SeqCst));
+    assert_eq!(io2.stats().queued_handlers, 1);
+    assert_eq!(io2.run(), 1);
+    assert!(called.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_multithread_posting() {
+    use std::thread;
+    use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
+
+    static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let io = &IoService::new();
+    let mut thrds = Vec::new();
+    for _ in 0..16 {
+        let io = io.clone();
+        thrds.push(thread::spawn(move || {
+            for _ in 0..1000 {
+                io.post(|io| {
+                    io.defer(|_| { COUNT.fetch_add(1, Ordering::SeqCst); });
+                });
+            }
+        }));
+    }
+    for thrd in thrds {
+        thrd.join().unwrap();
+    }
+
+    let mut thrds = Vec::new();
+    for _ in 0..16 {
+        let io = io.clone();
+        thrds.push(thread::spawn(move || io.run()));
+    }
+    let n: usize = thrds.into_iter().map(|thrd| thrd.join().unwrap()).sum();
+    assert_eq!(n, 32000);
+    assert_eq!(COUNT.load(Ordering::Relaxed), 16000);
+}
+
+#[cfg(test)]
+use std::sync::atomic::{Ordering, AtomicUsize};
+
+#[cfg(test)]
+struct CountingReactor(PollReactor, Arc<AtomicUsize>);
+
+#[cfg(test)]
+impl Reactor for CountingReactor {
+    fn register(&self, fd: RawFd, token: usize, intr: bool) {
+        self.0.register(fd, token, intr)
+    }
+
+    fn deregister(&self, fd: RawFd, token: usize) {
+        self.0.deregister(fd, token)
+    }
+
+    fn interest(&self, fd: RawFd, token: usize, input: bool, output: bool) {
+        self.0.interest(fd, token, input, output)
+    }
+
+    fn poll(&self, timeout: Option<i32>, events: &mut Vec<ReactorEvent>) {
+        self.1.fetch_add(1, Ordering::SeqCst);
+        self.0.poll(timeout, events)
+    }
+}
+
+#[test]
+fn test_leader_follower_idle() {
+    use std::thread;
+    use std::sync::mpsc::channel;
+
+    let polls = Arc::new(AtomicUsize::new(0));
+    let io = &IoService::with_reactor(CountingReactor(PollReactor::new(), polls.clone()));
+    let work = IoService::work(io);
+    let mut thrds = Vec::new();
+    for _ in 0..4 {
+        let io = io.clone();
+        thrds.push(thread::spawn(move || io.run()));
+    }
+
+    thread::sleep(Duration::from_millis(300));
+    assert!(polls.load(Ordering::SeqCst) <= 2);
+
+    let (tx, rx) = channel();
+    let now = Instant::now();
+    io.post(move |_| tx.send(()).unwrap());
+    rx.recv().unwrap();
+    assert!(now.elapsed() < Duration::from_millis(100));
+
+    drop(work);
+    for thrd in thrds {
+        thrd.join().unwrap();
+    }
+}
+
+#[test]
+fn test_graceful_stop() {
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
+    use ip::{IpAddrV4, Tcp, TcpEndpoint, TcpSocket, TcpListener};
+
+    static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let io = &IoService::new();
+    let sv = Arc::new(TcpListener::new(io, Tcp::v4()).unwrap());
+    sv.bind(&TcpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+    sv.listen().unwrap();
+    sv.async_accept(wrap(|sv: Arc<TcpListener>, res: io::Result<(TcpSocket, TcpEndpoint)>| {
+        assert!(res.is_err());
+        COUNT.fetch_add(1, Ordering::SeqCst);
+        sv.async_accept(wrap(|_: Arc<TcpListener>, res: io::Result<(TcpSocket, TcpEndpoint)>| {
+            assert!(res.is_err());
+            COUNT.fetch_add(1, Ordering::SeqCst);
+        }, &sv));
+    }, &sv));
+    io.post(|io| io.graceful_stop(Duration::new(10, 0)));
+    io.post(|_| { COUNT.fetch_add(1, Ordering::SeqCst); });
+
+    let now = Instant::now();
+    io.run();
+    assert!(now.elapsed() < Duration::new(10, 0));
+    assert_eq!(io.stopped(), true);
+    assert_eq!(COUNT.load(Ordering::Relaxed), 3);
+}
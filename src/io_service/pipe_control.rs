@@ -1,5 +1,6 @@
+use std::cmp;
 use std::sync::Mutex;
-use libc::{c_int, c_void, write, timespec};
+use libc::{c_int, c_void, write};
 use super::{IoService, IntrActor, RawFd, AsRawFd};
 use clock::Expiry;
 
@@ -77,13 +78,20 @@ impl Control {
         do_interrupt(ctrl.pipe_wfd.as_raw_fd());
     }
 
-    // 満了時間と現在時刻との差を返す.
-    // データは reactor に依存する
-    pub fn wait_duration(&self, max: i32) -> timespec {
-        // TODO: 満了時間と現在時刻との差を返す.
-        timespec {
-            tv_sec: 0,
-            tv_nsec: 0,
+    // 満了時間までの残り時間 (ミリ秒、切り上げ) と max の短い方を返す.
+    // max が -1 でタイマーが設定されていなければ -1 (無期限) を返す.
+    pub fn wait_duration(&self, max: i32) -> i32 {
+        let ctrl = self.mutex.lock().unwrap();
+        if ctrl.expiry == Expiry::default() {
+            return max;
+        }
+        let dur = ctrl.expiry.duration_from(Expiry::now());
+        let msec = dur.as_secs().saturating_mul(1000) + ((dur.subsec_nanos() + 999999) / 1000000) as u64;
+        let msec = cmp::min(msec, i32::max_value() as u64) as i32;
+        if max < 0 {
+            msec
+        } else {
+            cmp::min(max, msec)
         }
     }
 }
@@ -99,3 +107,25 @@ fn test_pipe() {
     ctrl.interrupt();
     ctrl.reset_timeout(Expiry::now());
 }
+
+#[test]
+fn test_wait_duration() {
+    use std::time::{Duration, Instant};
+    use clock::IntoExpiry;
+
+    let ctrl = Control::new();
+    assert_eq!(ctrl.wait_duration(-1), -1);
+    assert_eq!(ctrl.wait_duration(100), 100);
+
+    ctrl.reset_timeout((Instant::now() + Duration::from_secs(60)).into_expiry());
+    let msec = ctrl.wait_duration(-1);
+    assert!(msec > 50000 && msec <= 60000);
+    assert_eq!(ctrl.wait_duration(100), 100);
+
+    ctrl.reset_timeout(Expiry::now());
+    assert_eq!(ctrl.wait_duration(-1), 0);
+    assert_eq!(ctrl.wait_duration(100), 0);
+
+    ctrl.reset_timeout(Expiry::default());
+    assert_eq!(ctrl.wait_duration(-1), -1);
+}
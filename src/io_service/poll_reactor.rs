@@ -0,0 +1,152 @@
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::sync::{Mutex};
+use std::sync::atomic::{Ordering, AtomicBool};
+use std::collections::HashMap;
+use super::{Reactor, ReactorEvent};
+use libc::{POLLIN, POLLOUT, POLLERR, POLLHUP, POLLNVAL, F_SETFL, O_NONBLOCK,
+           c_int, c_void, nfds_t, pollfd, poll, fcntl, pipe, read, write, close};
+
+struct PollData {
+    registered_entry: HashMap<usize, (RawFd, i16)>,
+}
+
+/// The `Reactor` backend using poll(2).
+///
+/// Since poll(2) is level-triggered, the file descriptors are watched only while
+/// they have pending operations.
+pub struct PollReactor {
+    mutex: Mutex<PollData>,
+    polling: AtomicBool,
+    pipe_rfd: RawFd,
+    pipe_wfd: RawFd,
+}
+
+impl PollReactor {
+    /// Returns a new `PollReactor`.
+    ///
+    /// # Panics
+    /// Panics if too many open files.
+    pub fn new() -> PollReactor {
+        let mut pipefd: [c_int; 2] = [0; 2];
+        libc_unwrap!(pipe(pipefd.as_mut_ptr()));
+        for &fd in &pipefd {
+            libc_unwrap!(fcntl(fd, F_SETFL, O_NONBLOCK));
+        }
+        PollReactor {
+            mutex: Mutex::new(PollData {
+                registered_entry: HashMap::new(),
+            }),
+            polling: AtomicBool::new(false),
+            pipe_rfd: pipefd[0],
+            pipe_wfd: pipefd[1],
+        }
+    }
+
+    // poll(2) で待っているスレッドは新しい監視対象を知らないので、割り込んで作り直させる.
+    fn wakeup(&self) {
+        if self.polling.load(Ordering::SeqCst) {
+            let buf = [1u8];
+            libc_ign!(write(self.pipe_wfd, buf.as_ptr() as *const c_void, buf.len()));
+        }
+    }
+}
+
+impl Reactor for PollReactor {
+    fn register(&self, fd: RawFd, token: usize, intr: bool) {
+        let mut react = self.mutex.lock().unwrap();
+        react.registered_entry.insert(token, (fd, if intr { POLLIN } else { 0 }));
+        if intr {
+            self.wakeup();
+        }
+    }
+
+    fn deregister(&self, _fd: RawFd, token: usize) {
+        let mut react = self.mutex.lock().unwrap();
+        react.registered_entry.remove(&token);
+    }
+
+    fn interest(&self, fd: RawFd, token: usize, input: bool, output: bool) {
+        let mut react = self.mutex.lock().unwrap();
+        if let Some(entry) = react.registered_entry.get_mut(&token) {
+            let mut events = 0;
+            if input {
+                events |= POLLIN;
+            }
+            if output {
+                events |= POLLOUT;
+            }
+            if entry.1 != events {
+                let wakeup = (events & !entry.1) != 0;
+                *entry = (fd, events);
+                if wakeup {
+                    self.wakeup();
+                }
+            }
+        }
+    }
+
+    fn poll(&self, timeout: Option<i32>, events: &mut Vec<ReactorEvent>) {
+        let mut fds = vec![pollfd { fd: self.pipe_rfd, events: POLLIN, revents: 0 }];
+        let mut tokens = vec![0];
+        {
+            let react = self.mutex.lock().unwrap();
+            for (&token, &(fd, ev)) in &react.registered_entry {
+                if ev != 0 {
+                    fds.push(pollfd { fd: fd, events: ev, revents: 0 });
+                    tokens.push(token);
+                }
+            }
+            self.polling.store(timeout.is_some(), Ordering::SeqCst);
+        }
+
+        let len = unsafe { poll(fds.as_mut_ptr(), fds.len() as nfds_t, timeout.unwrap_or(0)) };
+        self.polling.store(false, Ordering::SeqCst);
+
+        if len > 0 {
+            if fds[0].revents != 0 {
+                let mut buf: [u8; 64] = unsafe { mem::uninitialized() };
+                while unsafe { read(self.pipe_rfd, buf.as_mut_ptr() as *mut c_void, buf.len()) } > 0 {
+                }
+            }
+            for (pfd, &token) in fds.iter().zip(tokens.iter()).skip(1) {
+                if pfd.revents != 0 {
+                    events.push(ReactorEvent {
+                        token: token,
+                        readable: (pfd.revents & POLLIN) != 0,
+                        writable: (pfd.revents & POLLOUT) != 0,
+                        error: (pfd.revents & (POLLERR | POLLHUP | POLLNVAL)) != 0,
+                        result: None,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Drop for PollReactor {
+    fn drop(&mut self) {
+        libc_ign!(close(self.pipe_rfd));
+        libc_ign!(close(self.pipe_wfd));
+    }
+}
+
+#[cfg(test)]
+use io_service::IoService;
+#[cfg(test)]
+use super::reactor::backend_test;
+
+#[test]
+fn test_poll_timer() {
+    backend_test::timer(&IoService::with_reactor(PollReactor::new()));
+}
+
+#[test]
+fn test_poll_echo() {
+    backend_test::echo(&IoService::with_reactor(PollReactor::new()));
+}
+
+#[test]
+fn test_poll_cancel() {
+    backend_test::cancel(&IoService::with_reactor(PollReactor::new()));
+}
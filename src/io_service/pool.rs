@@ -0,0 +1,232 @@
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::sync::atomic::{Ordering, AtomicUsize};
+use libc;
+use super::{IoService, IoServiceWork};
+
+/// Provides a pool of threads that run `IoService`s.
+///
+/// The pool owns the worker threads, and stops and joins them when dropped.
+/// The worker threads are named `asyncio-N`.
+///
+/// # Examples
+/// Runs a single `IoService` by 4 threads:
+///
+/// ```
+/// use asyncio::{IoService, IoServicePool};
+/// use std::sync::mpsc::channel;
+///
+/// let pool = IoServicePool::new(4).unwrap();
+/// let (tx, rx) = channel();
+/// for i in 0..100 {
+///   let tx = tx.clone();
+///   pool.io_service().post(move |_| tx.send(i).unwrap());
+/// }
+///
+/// assert_eq!(rx.iter().take(100).fold(0, |sum, i| sum + i), 4950);
+/// ```
+///
+/// Runs one `IoService` per CPU core, and spreads the accepted sockets:
+///
+/// ```rust,no_run
+/// use asyncio::{IoService, IoServicePool};
+/// use asyncio::ip::{Tcp, TcpEndpoint, TcpListener};
+///
+/// let pool = IoServicePool::per_core().unwrap();
+/// let io = &IoService::new();
+/// let ep = TcpEndpoint::new(Tcp::v4(), 12345);
+/// let sv = TcpListener::new(io, Tcp::v4()).unwrap();
+/// sv.bind(&ep).unwrap();
+/// sv.listen().unwrap();
+/// loop {
+///   let (soc, _) = sv.accept_on(pool.io_service()).unwrap();
+///   /* do something */
+/// }
+/// ```
+pub struct IoServicePool {
+    ios: Vec<IoService>,
+    works: Vec<IoServiceWork>,
+    thrds: Vec<JoinHandle<()>>,
+    next: AtomicUsize,
+}
+
+impl IoServicePool {
+    /// Returns a pool of `nthreads` threads which share a single `IoService`.
+    ///
+    /// # Panics
+    /// Panics if `nthreads` is zero.
+    pub fn new(nthreads: usize) -> io::Result<IoServicePool> {
+        assert!(nthreads > 0);
+        let io = IoService::new();
+        Self::spawn(vec![io.clone()], (0..nthreads).map(|_| io.clone()).collect())
+    }
+
+    /// Returns a pool of `nthreads` threads, each of which runs its own `IoService`.
+    ///
+    /// # Panics
+    /// Panics if `nthreads` is zero.
+    pub fn per_thread(nthreads: usize) -> io::Result<IoServicePool> {
+        assert!(nthreads > 0);
+        let ios: Vec<IoService> = (0..nthreads).map(|_| IoService::new()).collect();
+        Self::spawn(ios.clone(), ios)
+    }
+
+    /// Returns a pool that runs one `IoService` per CPU core.
+    ///
+    /// On Linux, the cores are the ones allowed for the calling thread (e.g. restricted by cpuset or `taskset`),
+    /// and each thread is pinned to its CPU.
+    pub fn per_core() -> io::Result<IoServicePool> {
+        let cpus = cpu_list();
+        let pool = try!(Self::per_thread(cpus.len()));
+        if cfg!(target_os = "linux") {
+            for (i, &cpu) in cpus.iter().enumerate() {
+                try!(pool.set_cpu_affinity(i, cpu));
+            }
+        }
+        Ok(pool)
+    }
+
+    fn spawn(ios: Vec<IoService>, runs: Vec<IoService>) -> io::Result<IoServicePool> {
+        let works = ios.iter().map(|io| IoService::work(io)).collect();
+        let mut thrds = Vec::new();
+        for (i, io) in runs.into_iter().enumerate() {
+            let thrd = try!(thread::Builder::new()
+                            .name(format!("asyncio-{}", i))
+                            .spawn(move || { io.run(); }));
+            thrds.push(thrd);
+        }
+        Ok(IoServicePool {
+            ios: ios,
+            works: works,
+            thrds: thrds,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns a `IoService` of the pool, selected by round-robin.
+    pub fn io_service(&self) -> &IoService {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        &self.ios[i % self.ios.len()]
+    }
+
+    /// Returns a number of the `IoService`s.
+    pub fn len(&self) -> usize {
+        self.ios.len()
+    }
+
+    /// Returns a number of the worker threads.
+    pub fn threads(&self) -> usize {
+        self.thrds.len()
+    }
+
+    /// Pins the `index`-th worker thread to the given CPU.
+    #[cfg(target_os = "linux")]
+    pub fn set_cpu_affinity(&self, index: usize, cpu: usize) -> io::Result<()> {
+        use std::mem;
+        use std::os::unix::thread::JoinHandleExt;
+        use libc::{cpu_set_t, CPU_SET, CPU_ZERO, pthread_setaffinity_np};
+
+        let mut set: cpu_set_t = unsafe { mem::zeroed() };
+        unsafe {
+            CPU_ZERO(&mut set);
+            CPU_SET(cpu, &mut set);
+        }
+        let thrd = self.thrds[index].as_pthread_t();
+        match unsafe { pthread_setaffinity_np(thrd, mem::size_of::<cpu_set_t>(), &set) } {
+            0 => Ok(()),
+            ec => Err(io::Error::from_raw_os_error(ec)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn set_cpu_affinity(&self, _index: usize, _cpu: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Stops all of the `IoService`s immediately.
+    pub fn stop(&self) {
+        for io in &self.ios {
+            io.stop();
+        }
+    }
+
+    /// Releases the works of the `IoService`s, and joins the worker threads.
+    pub fn join(mut self) {
+        self.join_all();
+    }
+
+    fn join_all(&mut self) {
+        self.works.clear();
+        for thrd in self.thrds.drain(..) {
+            let _ = thrd.join();
+        }
+    }
+}
+
+impl Drop for IoServicePool {
+    fn drop(&mut self) {
+        self.join_all();
+    }
+}
+
+fn cpu_count() -> usize {
+    match unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) } {
+        n if n > 0 => n as usize,
+        _ => 1,
+    }
+}
+
+// 呼び出したスレッドが実行を許可されている CPU の一覧を返す.
+#[cfg(target_os = "linux")]
+fn cpu_list() -> Vec<usize> {
+    use std::mem;
+    use libc::{cpu_set_t, CPU_ISSET, CPU_SETSIZE, sched_getaffinity};
+
+    let mut set: cpu_set_t = unsafe { mem::zeroed() };
+    if unsafe { sched_getaffinity(0, mem::size_of::<cpu_set_t>(), &mut set) } != 0 {
+        return (0..cpu_count()).collect();
+    }
+    let cpus: Vec<usize> = (0..CPU_SETSIZE as usize).filter(|&cpu| unsafe { CPU_ISSET(cpu, &set) }).collect();
+    if cpus.is_empty() {
+        (0..cpu_count()).collect()
+    } else {
+        cpus
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_list() -> Vec<usize> {
+    (0..cpu_count()).collect()
+}
+
+#[test]
+fn test_pool_per_thread() {
+    use std::sync::mpsc::channel;
+
+    let pool = IoServicePool::per_thread(4).unwrap();
+    assert_eq!(pool.len(), 4);
+    assert_eq!(pool.threads(), 4);
+
+    let (tx, rx) = channel();
+    for _ in 0..100 {
+        let tx = tx.clone();
+        pool.io_service().post(move |_| tx.send(thread::current().name().unwrap().to_owned()).unwrap());
+    }
+    let mut names: Vec<String> = rx.iter().take(100).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names, ["asyncio-0", "asyncio-1", "asyncio-2", "asyncio-3"]);
+    pool.join();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_pool_per_core() {
+    let cpus = cpu_list();
+    assert!(!cpus.is_empty());
+    assert!(cpus.len() <= cpu_count());
+
+    let pool = IoServicePool::per_core().unwrap();
+    assert_eq!(pool.threads(), cpus.len());
+    pool.join();
+}
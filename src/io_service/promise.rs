@@ -0,0 +1,180 @@
+use std::io;
+use std::sync::{Arc, Mutex, Condvar};
+use std::time::{Duration, Instant};
+use error::{ErrCode, ECANCELED};
+use super::{IoService, Callback, Handler, AsyncResult};
+
+struct PromiseState<R> {
+    result: Mutex<Option<io::Result<R>>>,
+    condvar: Condvar,
+}
+
+impl<R> PromiseState<R> {
+    fn fulfill(&self, res: io::Result<R>) {
+        let mut result = self.result.lock().unwrap();
+        *result = Some(res);
+        self.condvar.notify_all();
+    }
+}
+
+/// The handler that fulfills a `Promise`.
+///
+/// If the handler is dropped without being called, the `Promise` completes with `ECANCELED`.
+pub struct UsePromise<R>(Option<Arc<PromiseState<R>>>);
+
+impl<R> Drop for UsePromise<R> {
+    fn drop(&mut self) {
+        if let Some(state) = self.0.take() {
+            state.fulfill(Err(ECANCELED.into()));
+        }
+    }
+}
+
+impl<R: Send + 'static> Handler<R> for UsePromise<R> {
+    type Output = Promise<R>;
+
+    fn callback(mut self, _: &IoService, res: io::Result<R>) {
+        if let Some(state) = self.0.take() {
+            state.fulfill(res);
+        }
+    }
+
+    fn wrap<G>(self, callback: G) -> Callback
+        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
+    {
+        Callback::new(move |io: *const IoService, ec| {
+            callback(unsafe { &*io }, ec, self)
+        })
+    }
+
+    type AsyncResult = UsePromiseAsyncResult<R>;
+
+    fn async_result(&self) -> Self::AsyncResult {
+        UsePromiseAsyncResult(self.0.as_ref().unwrap().clone())
+    }
+}
+
+pub struct UsePromiseAsyncResult<R>(Arc<PromiseState<R>>);
+
+impl<R> AsyncResult<Promise<R>> for UsePromiseAsyncResult<R> {
+    fn get(self, _io: &IoService) -> Promise<R> {
+        Promise(self.0)
+    }
+}
+
+/// The handle to the result of an asynchronous operation, which is waited in a blocking manner.
+///
+/// The operation completes by the `IoService` running in other threads.
+/// Waiting in a thread running the `IoService` may block forever.
+pub struct Promise<R>(Arc<PromiseState<R>>);
+
+impl<R> Promise<R> {
+    /// Blocks until the operation completes, and returns the result.
+    pub fn wait(self) -> io::Result<R> {
+        let mut result = self.0.result.lock().unwrap();
+        loop {
+            if let Some(res) = result.take() {
+                return res;
+            }
+            result = self.0.condvar.wait(result).unwrap();
+        }
+    }
+
+    /// Blocks until the operation completes or the timeout elapses.
+    ///
+    /// Returns `Err(self)` if timed out, and the `Promise` can be waited again.
+    pub fn wait_timeout(self, timeout: Duration) -> Result<io::Result<R>, Promise<R>> {
+        let expiry = Instant::now() + timeout;
+        {
+            let mut result = self.0.result.lock().unwrap();
+            loop {
+                if let Some(res) = result.take() {
+                    return Ok(res);
+                }
+                let now = Instant::now();
+                if expiry <= now {
+                    break;
+                }
+                result = self.0.condvar.wait_timeout(result, expiry - now).unwrap().0;
+            }
+        }
+        Err(self)
+    }
+
+    /// Returns true if the operation has completed.
+    pub fn is_ready(&self) -> bool {
+        self.0.result.lock().unwrap().is_some()
+    }
+}
+
+/// Provides a blocking `Promise` handler to asynchronous operation.
+///
+/// The UsePromise has trait the `Handler`, that type of `Handler::Output` is `Promise<R>`.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use std::time::Duration;
+/// use asyncio::{IoService, SteadyTimer, use_promise};
+///
+/// let io = &IoService::new();
+/// let work = IoService::work(io);
+/// let thrd = {
+///     let io = io.clone();
+///     thread::spawn(move || io.run())
+/// };
+///
+/// let timer = SteadyTimer::new(io);
+/// let promise = timer.async_wait_for(Duration::from_millis(1), use_promise());
+/// assert!(promise.wait().is_ok());
+///
+/// drop(work);
+/// thrd.join().unwrap();
+/// ```
+pub fn use_promise<R>() -> UsePromise<R> {
+    UsePromise(Some(Arc::new(PromiseState {
+        result: Mutex::new(None),
+        condvar: Condvar::new(),
+    })))
+}
+
+#[test]
+fn test_promise_wait_timeout() {
+    use std::thread;
+    use waitable_timer::SteadyTimer;
+
+    let io = &IoService::new();
+    let work = IoService::work(io);
+    let thrd = {
+        let io = io.clone();
+        thread::spawn(move || io.run())
+    };
+
+    let timer = SteadyTimer::new(io);
+    let promise = timer.async_wait_for(Duration::new(60, 0), use_promise());
+    let promise = match promise.wait_timeout(Duration::from_millis(10)) {
+        Ok(_) => panic!(),
+        Err(promise) => promise,
+    };
+    assert!(!promise.is_ready());
+
+    timer.cancel();
+    assert!(promise.wait_timeout(Duration::new(10, 0)).ok().unwrap().is_err());
+
+    drop(work);
+    thrd.join().unwrap();
+}
+
+#[test]
+fn test_promise_dropped_handler() {
+    use waitable_timer::SteadyTimer;
+
+    let io = IoService::new();
+    let timer = SteadyTimer::new(&io);
+    let promise = timer.async_wait_for(Duration::new(60, 0), use_promise::<()>());
+    drop(timer);
+    drop(io);
+    let err: io::Error = ECANCELED.into();
+    assert_eq!(promise.wait().unwrap_err().raw_os_error(), err.raw_os_error());
+}
@@ -0,0 +1,821 @@
+use std::mem;
+use std::panic::{UnwindSafe, RefUnwindSafe};
+use std::os::unix::io::{RawFd, AsRawFd};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{Ordering, AtomicUsize};
+use std::collections::{HashSet, VecDeque};
+use error::{ErrCode, READY, ECANCELED, EAGAIN, sock_error, completion};
+use unsafe_cell::UnsafeBoxedCell;
+use clock::Expiry;
+use libc::{self, c_void, iovec, sockaddr_storage, socklen_t, close, read};
+use super::{IoObject, IoService, Callback, CancelState};
+
+/// An event that is reported by the `Reactor`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ReactorEvent {
+    /// The token that was given to `Reactor::register`.
+    pub token: usize,
+
+    /// The file descriptor is ready to read.
+    pub readable: bool,
+
+    /// The file descriptor is ready to write.
+    pub writable: bool,
+
+    /// An error or a hangup occured on the file descriptor (e.g. `EPOLLERR` or `EPOLLHUP`).
+    pub error: bool,
+
+    /// The result of the operation given to `Reactor::submit`, that is the return value or the negated `errno`.
+    ///
+    /// The completion of an input operation is reported with `readable`, and the output operation with `writable`.
+    /// It is `None` if the event is the readiness.
+    pub result: Option<i32>,
+}
+
+/// An I/O operation that is performed by the `Reactor` itself, instead of waiting the readiness.
+///
+/// The buffers that are pointed by the operation are valid until its completion is reported.
+#[derive(Clone, Copy)]
+pub enum ReactorOp {
+    /// `read(2)` into the buffer.
+    Read(*mut u8, usize),
+
+    /// `write(2)` from the buffer.
+    Write(*const u8, usize),
+
+    /// `readv(2)` into the `iovec`s.
+    ReadV(*const iovec, usize),
+
+    /// `writev(2)` from the `iovec`s.
+    WriteV(*const iovec, usize),
+
+    /// `accept(2)`. The result is the accepted file descriptor.
+    Accept,
+
+    /// `connect(2)` to the address.
+    Connect(sockaddr_storage, socklen_t),
+}
+
+/// Traits to the backend of `IoService` which waits the readiness of file descriptors.
+///
+/// The readiness should be reported in edge-triggered manner.
+/// A backend which can only report in level-triggered manner, should watch only the interests
+/// given by `Reactor::interest`.
+pub trait Reactor : Send + Sync + 'static {
+    /// Registers a file descriptor.
+    ///
+    /// If `intr` is true, the file descriptor is an internal interrupter (e.g. `eventfd`)
+    /// which should be watched for reading in level-triggered manner.
+    fn register(&self, fd: RawFd, token: usize, intr: bool);
+
+    /// Deregisters a file descriptor.
+    ///
+    /// The events of the `token` that are reported after this, are ignored.
+    fn deregister(&self, fd: RawFd, token: usize);
+
+    /// Notifies whether the file descriptor has pending input or output operations.
+    fn interest(&self, _fd: RawFd, _token: usize, _input: bool, _output: bool) {
+    }
+
+    /// Waits the events and pushes them to `events`.
+    ///
+    /// The `timeout` is `None` to return immediately, `Some(-1)` to wait infinitely,
+    /// or otherwise the maximum milliseconds to wait.
+    fn poll(&self, timeout: Option<i32>, events: &mut Vec<ReactorEvent>);
+
+    /// Returns true if the backend performs the operation by itself (e.g. io_uring).
+    ///
+    /// Such operations are given to `Reactor::submit` instead of waiting the readiness.
+    fn performs(&self, _op: &ReactorOp) -> bool {
+        false
+    }
+
+    /// Starts the operation on the file descriptor, that `Reactor::performs` returned true.
+    ///
+    /// The completion should be reported by `poll` with `ReactorEvent::result`.
+    /// At most one operation is submitted for each of the input and the output of the file descriptor.
+    fn submit(&self, _fd: RawFd, _token: usize, _input: bool, _op: ReactorOp) {
+    }
+
+    /// Requests to cancel the operation given to `Reactor::submit`.
+    ///
+    /// The completion of the operation (e.g. `ECANCELED`) should be still reported by `poll`.
+    fn abort(&self, _fd: RawFd, _token: usize, _input: bool) {
+    }
+
+    /// Notifies the earliest expiry of the timers.
+    ///
+    /// Returns true if the backend wakes up `poll` at the expiry by itself,
+    /// otherwise `IoService` wakes it up by the timerfd or the timeout of `poll`.
+    fn reset_timeout(&self, _expiry: Expiry) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+struct Op {
+    ops: VecDeque<Callback>,
+    ready: bool,
+    canceling: bool,
+    // 先頭の操作をバックエンドに投入し、その完了を待っている.
+    submitted: bool,
+    // 投入した操作を取り消したときに、ECANCELED の代わりにハンドラに渡すエラー.
+    aborted: Option<ErrCode>,
+    // 完了した操作のハンドラが、次の操作に投入を引き継いでいる. 新しい操作はその後ろに並べる.
+    passing: bool,
+}
+
+impl Op {
+    // 投入済みの操作はその完了でだけ取り出すので、待ち行列の操作はその後ろから扱う.
+    fn front(&self) -> usize {
+        if self.submitted { 1 } else { 0 }
+    }
+}
+
+struct Entry {
+    fd: RawFd,
+    intr: bool,
+    accept: bool,
+    input: Op,
+    output: Op,
+}
+
+impl Entry {
+    fn token(&self) -> usize {
+        self as *const _ as usize
+    }
+}
+
+struct ReactData {
+    callback_count: usize,
+    registered_entry: HashSet<*mut Entry>,
+    intr_entry: HashSet<*mut Entry>,
+    events: Vec<ReactorEvent>,
+}
+
+unsafe impl Send for ReactData {
+}
+
+unsafe impl Sync for ReactData {
+}
+
+/// 各バックエンドに共通する操作キューの管理.
+pub struct ReactorCore {
+    backend: Box<Reactor>,
+    mutex: Mutex<ReactData>,
+    wakeups: AtomicUsize,
+}
+
+impl UnwindSafe for ReactorCore {
+}
+
+impl RefUnwindSafe for ReactorCore {
+}
+
+impl ReactorCore {
+    pub fn new(backend: Box<Reactor>) -> ReactorCore {
+        ReactorCore {
+            backend: backend,
+            mutex: Mutex::new(ReactData {
+                callback_count: 0,
+                registered_entry: HashSet::new(),
+                intr_entry: HashSet::new(),
+                // 最初のイベントを受け取るときに確保しないように、あらかじめ用意しておく.
+                events: Vec::with_capacity(128),
+            }),
+            wakeups: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn poll(&self, timeout: Option<i32>, io: &IoService) -> usize {
+        let mut events = mem::replace(&mut self.mutex.lock().unwrap().events, Vec::new());
+        events.clear();
+        self.backend.poll(timeout, &mut events);
+
+        let mut react = self.mutex.lock().unwrap();
+        if !events.is_empty() {
+            self.wakeups.fetch_add(1, Ordering::Relaxed);
+        }
+        self.dispatch(&mut react, io, &events);
+        react.events = events;
+        react.callback_count
+    }
+
+    fn dispatch(&self, react: &mut ReactData, io: &IoService, events: &[ReactorEvent]) {
+        for ev in events {
+            let ptr = ev.token as *mut Entry;
+            if react.intr_entry.contains(&ptr) {
+                if ev.readable {
+                    let fd = unsafe { &*ptr }.fd;
+                    let mut buf: [u8; 8] = unsafe { mem::uninitialized() };
+                    libc_ign!(read(fd, buf.as_mut_ptr() as *mut c_void, buf.len()));
+                }
+                continue;
+            }
+            // 解除済みのエントリは無視する.
+            if !react.registered_entry.contains(&ptr) {
+                continue;
+            }
+
+            let ptr = unsafe { &mut *ptr };
+            if let Some(res) = ev.result {
+                let op = if ev.readable { &mut ptr.input } else { &mut ptr.output };
+                Self::complete(react, io, op, res);
+            } else if ev.error {
+                let ec = sock_error(ptr.fd);
+                for op in [&mut ptr.input, &mut ptr.output].iter_mut() {
+                    let front = op.front();
+                    for callback in op.ops.drain(front..) {
+                        react.callback_count -= 1;
+                        io.0.post_callback(callback, ec);
+                    }
+                }
+            } else {
+                // 投入済みの操作があれば、準備ができてもその完了を待つ.
+                if ev.readable && !ptr.input.submitted {
+                    if let Some(callback) = ptr.input.ops.pop_front() {
+                        react.callback_count -= 1;
+                        io.0.post_callback(callback, READY);
+                        ptr.input.ready = false;
+                    } else {
+                        ptr.input.ready = true;
+                    }
+                }
+                if ev.writable && !ptr.output.submitted {
+                    if let Some(callback) = ptr.output.ops.pop_front() {
+                        react.callback_count -= 1;
+                        io.0.post_callback(callback, READY);
+                        ptr.output.ready = false;
+                    } else {
+                        ptr.output.ready = true;
+                    }
+                }
+            }
+            self.interest(ptr);
+        }
+    }
+
+    // 投入した操作の完了をハンドラに渡す.
+    fn complete(react: &mut ReactData, io: &IoService, op: &mut Op, res: i32) {
+        if !op.submitted {
+            return;
+        }
+        op.submitted = false;
+        let aborted = op.aborted.take();
+        let callback = op.ops.pop_front().unwrap();
+        op.passing = !op.ops.is_empty();
+        react.callback_count -= 1;
+        match aborted {
+            Some(ec) if res == -libc::ECANCELED => io.0.post_callback(callback, ec),
+            _ => io.0.post_callback(callback, completion(res)),
+        }
+    }
+
+    // 投入済みの操作を ec で取り消す. 完了はバックエンドから届く.
+    fn abort(&self, fd: RawFd, token: usize, op: &mut Op, input: bool, ec: ErrCode) {
+        if op.submitted && op.aborted.is_none() {
+            op.aborted = Some(ec);
+            self.backend.abort(fd, token, input);
+        }
+    }
+
+    fn submitting(react: &ReactData) -> bool {
+        react.registered_entry.iter().any(|&ptr| unsafe { &*ptr }.input.submitted || unsafe { &*ptr }.output.submitted)
+    }
+
+    // 取り消した操作の完了を待つ. カーネルが使い終わる前に返ると、ハンドラがバッファを解放してしまう.
+    // 他のスレッドが完了を受け取ることもあるので、ロックを外して少しずつ待つ.
+    fn wait_aborted<'a, F>(&'a self, mut react: MutexGuard<'a, ReactData>, io: &IoService, submitting: F) -> MutexGuard<'a, ReactData>
+        where F: Fn(&ReactData) -> bool
+    {
+        while submitting(&react) {
+            let mut events = mem::replace(&mut react.events, Vec::new());
+            drop(react);
+            events.clear();
+            self.backend.poll(Some(1), &mut events);
+            react = self.mutex.lock().unwrap();
+            self.dispatch(&mut react, io, &events);
+            react.events = events;
+        }
+        react
+    }
+
+    fn op_submitting(react: &ReactData, ptr: *mut Entry, input: bool) -> bool {
+        react.registered_entry.contains(&ptr) && {
+            let ptr = unsafe { &*ptr };
+            if input { ptr.input.submitted } else { ptr.output.submitted }
+        }
+    }
+
+    pub fn cancel_all(&self, io: &IoService) {
+        let react = self.mutex.lock().unwrap();
+        for &ptr in &react.registered_entry {
+            let ptr = unsafe { &mut *ptr };
+            let (fd, token) = (ptr.fd, ptr.token());
+            let front = ptr.input.front();
+            for callback in ptr.input.ops.drain(front..) {
+                io.0.post_callback(callback, ECANCELED);
+            }
+            self.abort(fd, token, &mut ptr.input, true, ECANCELED);
+            let front = ptr.output.front();
+            for callback in ptr.output.ops.drain(front..) {
+                io.0.post_callback(callback, ECANCELED);
+            }
+            self.abort(fd, token, &mut ptr.output, false, ECANCELED);
+            self.interest(ptr);
+        }
+        let mut react = self.wait_aborted(react, io, Self::submitting);
+        react.callback_count = 0;
+    }
+
+    pub fn cancel_accept(&self, io: &IoService) {
+        let mut react = self.mutex.lock().unwrap();
+        let mut count = 0;
+        for &ptr in &react.registered_entry {
+            let ptr = unsafe { &mut *ptr };
+            if ptr.accept {
+                let (fd, token) = (ptr.fd, ptr.token());
+                let front = ptr.input.front();
+                count += ptr.input.ops.len() - front;
+                ptr.input.canceling = true;
+                for callback in ptr.input.ops.drain(front..) {
+                    io.0.post_callback(callback, ECANCELED);
+                }
+                self.abort(fd, token, &mut ptr.input, true, ECANCELED);
+                self.interest(ptr);
+            }
+        }
+        react.callback_count -= count;
+    }
+
+    pub fn stats(&self) -> (usize, usize, usize) {
+        let react = self.mutex.lock().unwrap();
+        let mut input = 0;
+        let mut output = 0;
+        for ptr in &react.registered_entry {
+            input += unsafe { &**ptr }.input.ops.len();
+            output += unsafe { &**ptr }.output.ops.len();
+        }
+        (react.registered_entry.len(), input, output)
+    }
+
+    // 完了を待っている操作の数を返す.
+    pub fn pending(&self) -> usize {
+        self.mutex.lock().unwrap().callback_count
+    }
+
+    pub fn wakeups(&self) -> usize {
+        self.wakeups.load(Ordering::Relaxed)
+    }
+
+    fn interest(&self, ptr: &Entry) {
+        self.backend.interest(ptr.fd, ptr.token(), !ptr.input.ops.is_empty(), !ptr.output.ops.is_empty());
+    }
+
+    fn register(&self, ptr: &mut Entry) {
+        let mut react = self.mutex.lock().unwrap();
+        if ptr.intr {
+            react.intr_entry.insert(ptr);
+        } else {
+            react.registered_entry.insert(ptr);
+        }
+        self.backend.register(ptr.fd, ptr.token(), ptr.intr);
+    }
+
+    fn unregister(&self, ptr: &mut Entry) {
+        let mut react = self.mutex.lock().unwrap();
+        if ptr.intr {
+            react.intr_entry.remove(&(ptr as *mut _));
+        } else {
+            assert!(ptr.input.ops.is_empty());
+            assert!(ptr.output.ops.is_empty());
+            assert!(react.registered_entry.remove(&(ptr as *mut _)));
+        }
+        self.backend.deregister(ptr.fd, ptr.token());
+    }
+
+    fn add_op(&self, io: &IoService, ptr: &Entry, op: &mut Op, callback: Callback, ec: ErrCode) -> Result<Option<Callback>, Vec<Callback>> {
+        let mut react = self.mutex.lock().unwrap();
+        let input = op as *const Op == &ptr.input as *const Op;
+        let cancelled = callback.cancel_states().iter()
+            .filter_map(|&(ref state, id)| state.register(id, io, ptr.token(), input).err())
+            .next();
+        if let Some(ec) = cancelled {
+            io.0.post_callback(callback, ec);
+            return Ok(None);
+        }
+        let front = op.front();
+        if op.canceling && ec == EAGAIN {
+            react.callback_count -= op.ops.len() - front;
+            op.ops.insert(front, callback);
+            Err(op.ops.drain(front..).collect())
+        } else {
+            op.canceling = false;
+            if op.ready {
+                op.ready = false;
+                if op.ops.len() == front || ec == EAGAIN {
+                    Ok(Some(callback))
+                } else {
+                    op.ops.push_back(callback);
+                    Ok(op.ops.remove(front))
+                }
+            } else {
+                op.ready = false;
+                react.callback_count += 1;
+                if ec == EAGAIN {
+                    op.ops.insert(front, callback);
+                } else {
+                    op.ops.push_back(callback);
+                }
+                self.interest(ptr);
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn performs(&self, op: &ReactorOp) -> bool {
+        self.backend.performs(op)
+    }
+
+    // バックエンドが実行する操作を積む. ec が READY なら新しい操作で、他の操作がなければすぐに投入する.
+    // EINPROGRESS なら開始した操作で、待ち行列の先頭からバックエンドに投入する.
+    fn submit_op(&self, io: &IoService, ptr: &Entry, op: &mut Op, callback: Callback, rop: ReactorOp, ec: ErrCode) -> Result<(), Vec<Callback>> {
+        let mut react = self.mutex.lock().unwrap();
+        let input = op as *const Op == &ptr.input as *const Op;
+        let cancelled = callback.cancel_states().iter()
+            .filter_map(|&(ref state, id)| state.register(id, io, ptr.token(), input).err())
+            .next();
+        if let Some(ec) = cancelled {
+            io.0.post_callback(callback, ec);
+            return Ok(());
+        }
+        if ec == READY {
+            op.canceling = false;
+            if !op.ops.is_empty() || op.passing {
+                react.callback_count += 1;
+                op.ops.push_back(callback);
+                return Ok(());
+            }
+        }
+        let front = op.front();
+        if op.canceling {
+            react.callback_count -= op.ops.len() - front;
+            op.ops.insert(front, callback);
+            return Err(op.ops.drain(front..).collect());
+        }
+        react.callback_count += 1;
+        if op.submitted {
+            // 他の操作の完了を待ってから投入し直す.
+            op.ops.insert(front, callback);
+        } else {
+            op.ops.push_front(callback);
+            op.submitted = true;
+            op.passing = false;
+            self.backend.submit(ptr.fd, ptr.token(), input, rop);
+        }
+        self.interest(ptr);
+        Ok(())
+    }
+
+    pub fn reset_timeout(&self, expiry: Expiry) -> bool {
+        self.backend.reset_timeout(expiry)
+    }
+
+    // CancellationSignal に結びついた操作だけをキャンセルする.
+    pub fn cancel_op(&self, io: &IoService, token: usize, input: bool, state: &Arc<CancelState>, id: usize, ec: ErrCode) {
+        let mut react = self.mutex.lock().unwrap();
+        let ptr = token as *mut Entry;
+        if !react.registered_entry.contains(&ptr) {
+            return;
+        }
+        let ptr = unsafe { &mut *ptr };
+        let pos = {
+            let op = if input { &ptr.input } else { &ptr.output };
+            op.ops.iter().position(|callback| {
+                callback.cancel_states().iter().any(|&(ref st, i)| Arc::ptr_eq(st, state) && i == id)
+            })
+        };
+        if let Some(pos) = pos {
+            let (fd, token) = (ptr.fd, ptr.token());
+            let op = if input { &mut ptr.input } else { &mut ptr.output };
+            if pos == 0 && op.submitted {
+                self.abort(fd, token, op, input, ec);
+                drop(self.wait_aborted(react, io, |react| Self::op_submitting(react, token as *mut Entry, input)));
+                return;
+            }
+            let callback = op.ops.remove(pos).unwrap();
+            react.callback_count -= 1;
+            self.interest(ptr);
+            io.0.post_callback(callback, ec);
+        }
+    }
+
+    fn next_op(&self, ptr: &Entry, op: &mut Op) -> Option<Result<Callback, Vec<Callback>>> {
+        let mut react = self.mutex.lock().unwrap();
+        let front = op.front();
+        let res = if !op.canceling {
+            if op.ops.len() > front {
+                react.callback_count -= 1;
+                op.ops.remove(front).map(Ok)
+            } else {
+                op.ready = true;
+                op.passing = false;
+                None
+            }
+        } else {
+            op.canceling = false;
+            op.ready = true;
+            op.passing = false;
+            let len = op.ops.len() - front;
+            react.callback_count -= len;
+            if len > 0 {
+                Some(Err(op.ops.drain(front..).collect()))
+            } else {
+                None
+            }
+        };
+        self.interest(ptr);
+        res
+    }
+
+    fn del_ops(&self, io: &IoService, ptr: &Entry, op: &mut Op) -> Vec<Callback> {
+        let mut react = self.mutex.lock().unwrap();
+        let input = op as *const Op == &ptr.input as *const Op;
+        let front = op.front();
+        let ops: Vec<Callback> = op.ops.drain(front..).collect();
+        react.callback_count -= ops.len();
+        op.canceling = true;
+        self.abort(ptr.fd, ptr.token(), op, input, ECANCELED);
+        self.interest(ptr);
+        let token = ptr.token();
+        drop(self.wait_aborted(react, io, |react| Self::op_submitting(react, token as *mut Entry, input)));
+        ops
+    }
+}
+
+
+pub struct IntrActor {
+    ptr: UnsafeBoxedCell<Entry>,
+}
+
+impl IntrActor {
+    pub fn new(fd: RawFd) -> IntrActor {
+        IntrActor {
+            ptr: UnsafeBoxedCell::new(Entry {
+                fd: fd,
+                intr: true,
+                accept: false,
+                input: Op::default(),
+                output: Op::default(),
+            })
+        }
+    }
+
+    pub fn set_intr(&self, io: &IoService) {
+        io.0.react.register(unsafe { self.ptr.get() });
+    }
+
+    pub fn unset_intr(&self, io: &IoService) {
+        io.0.react.unregister(unsafe { self.ptr.get() });
+    }
+}
+
+impl AsRawFd for IntrActor {
+    fn as_raw_fd(&self) -> RawFd {
+        unsafe { self.ptr.get() }.fd
+    }
+}
+
+impl Drop for IntrActor {
+    fn drop(&mut self) {
+        let ptr = unsafe { self.ptr.get() };
+        libc_ign!(close(ptr.fd));
+    }
+}
+
+
+pub struct IoActor {
+    io: IoService,
+    ptr: UnsafeBoxedCell<Entry>,
+}
+
+impl IoActor {
+    pub fn new(io: &IoService, fd: RawFd) -> IoActor {
+        Self::with_accept(io, fd, false)
+    }
+
+    pub fn new_acceptor(io: &IoService, fd: RawFd) -> IoActor {
+        Self::with_accept(io, fd, true)
+    }
+
+    fn with_accept(io: &IoService, fd: RawFd, accept: bool) -> IoActor {
+        let ptr = UnsafeBoxedCell::new(Entry {
+            fd: fd,
+            intr: false,
+            accept: accept,
+            input: Op::default(),
+            output: Op::default(),
+        });
+        io.0.react.register(unsafe { ptr.get() });
+        IoActor { io: io.clone(), ptr: ptr }
+    }
+
+    pub fn add_input(&self, callback: Callback, ec: ErrCode) {
+        let ptr = unsafe { self.ptr.get() };
+        if ptr.accept && self.io.0.draining() {
+            self.io.0.post_callback(callback, ECANCELED);
+            return;
+        }
+        match self.io.0.react.add_op(&self.io, ptr, &mut unsafe { self.ptr.get() }.input, callback, ec) {
+            Ok(Some(callback)) =>
+                self.io.0.post_callback(callback, READY),
+            Err(callbacks) =>
+                for callback in callbacks {
+                    self.io.0.post_callback(callback, ECANCELED);
+                },
+            _ => (),
+        }
+    }
+
+    pub fn add_output(&self, callback: Callback, ec: ErrCode) {
+        let ptr = unsafe { self.ptr.get() };
+        match self.io.0.react.add_op(&self.io, ptr, &mut unsafe { self.ptr.get() }.output, callback, ec) {
+            Ok(Some(callback)) =>
+                self.io.0.post_callback(callback, READY),
+            Err(callbacks) =>
+                for callback in callbacks {
+                    self.io.0.post_callback(callback, ECANCELED);
+                },
+            _ => (),
+        }
+    }
+
+    pub fn performs(&self, op: &ReactorOp) -> bool {
+        self.io.0.react.performs(op)
+    }
+
+    pub fn submit_input(&self, callback: Callback, op: ReactorOp, ec: ErrCode) {
+        let ptr = unsafe { self.ptr.get() };
+        if ptr.accept && self.io.0.draining() {
+            self.io.0.post_callback(callback, ECANCELED);
+            return;
+        }
+        if let Err(callbacks) = self.io.0.react.submit_op(&self.io, ptr, &mut unsafe { self.ptr.get() }.input, callback, op, ec) {
+            for callback in callbacks {
+                self.io.0.post_callback(callback, ECANCELED);
+            }
+        }
+    }
+
+    pub fn submit_output(&self, callback: Callback, op: ReactorOp, ec: ErrCode) {
+        let ptr = unsafe { self.ptr.get() };
+        if let Err(callbacks) = self.io.0.react.submit_op(&self.io, ptr, &mut unsafe { self.ptr.get() }.output, callback, op, ec) {
+            for callback in callbacks {
+                self.io.0.post_callback(callback, ECANCELED);
+            }
+        }
+    }
+
+    pub fn next_input(&self) {
+        let ptr = unsafe { self.ptr.get() };
+        match self.io.0.react.next_op(ptr, &mut unsafe { self.ptr.get() }.input) {
+            Some(Ok(callback)) =>
+                self.io.0.post_callback(callback, READY),
+            Some(Err(callbacks)) =>
+                for callback in callbacks {
+                    self.io.0.post_callback(callback, ECANCELED);
+                },
+            _ => (),
+        }
+    }
+
+    pub fn next_output(&self) {
+        let ptr = unsafe { self.ptr.get() };
+        match self.io.0.react.next_op(ptr, &mut unsafe { self.ptr.get() }.output) {
+            Some(Ok(callback)) =>
+                self.io.0.post_callback(callback, READY),
+            Some(Err(callbacks)) =>
+                for callback in callbacks {
+                    self.io.0.post_callback(callback, ECANCELED);
+                },
+            _ => (),
+        }
+    }
+
+    pub fn del_input(&self) -> Vec<Callback> {
+        let ptr = unsafe { self.ptr.get() };
+        self.io.0.react.del_ops(&self.io, ptr, &mut unsafe { self.ptr.get() }.input)
+    }
+
+    pub fn del_output(&self) -> Vec<Callback> {
+        let ptr = unsafe { self.ptr.get() };
+        self.io.0.react.del_ops(&self.io, ptr, &mut unsafe { self.ptr.get() }.output)
+    }
+}
+
+unsafe impl IoObject for IoActor {
+    fn io_service(&self) -> &IoService {
+        &self.io
+    }
+}
+
+impl AsRawFd for IoActor {
+    fn as_raw_fd(&self) -> RawFd {
+        unsafe { self.ptr.get() }.fd
+    }
+}
+
+impl Drop for IoActor {
+    fn drop(&mut self) {
+        let ptr = unsafe { self.ptr.get() };
+        self.io.0.react.unregister(ptr);
+        libc_ign!(close(ptr.fd));
+    }
+}
+
+// 各バックエンドで同じシナリオを走らせて、振る舞いを突き合わせる.
+#[cfg(test)]
+pub mod backend_test {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use io_service::{IoService, wrap};
+    use ip::{IpAddrV4, Tcp, TcpEndpoint, TcpSocket, TcpListener};
+    use local::{LocalStream, LocalStreamSocket, connect_pair};
+    use stream::Stream;
+    use waitable_timer::SteadyTimer;
+    use libc;
+
+    pub fn timer(io: &IoService) {
+        let timer = Arc::new(SteadyTimer::new(io));
+        let now = Instant::now();
+        timer.async_wait_for(Duration::from_millis(50), wrap(|_: Arc<SteadyTimer>, res: io::Result<()>| res.unwrap(), &timer));
+        io.run();
+        assert!(now.elapsed() >= Duration::from_millis(50));
+        assert!(now.elapsed() < Duration::from_secs(5));
+    }
+
+    pub fn echo(io: &IoService) {
+        let sv = Arc::new(TcpListener::new(io, Tcp::v4()).unwrap());
+        sv.bind(&TcpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+        sv.listen().unwrap();
+        let ep = sv.local_endpoint().unwrap();
+
+        sv.async_accept(wrap(move |_: Arc<TcpListener>, res: io::Result<(TcpSocket, TcpEndpoint)>| {
+            let (soc, _) = res.unwrap();
+            let soc = Arc::new(soc);
+            let buf = Arc::new(Mutex::new([0; 16]));
+            let buf_ = buf.clone();
+            soc.async_read_some(&mut *buf.lock().unwrap(), wrap(move |soc: Arc<TcpSocket>, res: io::Result<usize>| {
+                let len = res.unwrap();
+                soc.write_some(&buf_.lock().unwrap()[..len]).unwrap();
+            }, &soc));
+        }, &sv));
+
+        let cl = Arc::new(TcpSocket::new(io, Tcp::v4()).unwrap());
+        let res = Arc::new(Mutex::new(Vec::new()));
+        let res_ = res.clone();
+        cl.async_connect(&ep, wrap(move |cl: Arc<TcpSocket>, r: io::Result<()>| {
+            r.unwrap();
+            cl.write_some(b"hello").unwrap();
+            let buf = Arc::new(Mutex::new([0; 16]));
+            let buf_ = buf.clone();
+            let res_ = res_.clone();
+            cl.async_read_some(&mut *buf.lock().unwrap(), wrap(move |_: Arc<TcpSocket>, r: io::Result<usize>| {
+                res_.lock().unwrap().extend_from_slice(&buf_.lock().unwrap()[..r.unwrap()]);
+            }, &cl));
+        }, &cl));
+        io.run();
+        assert_eq!(&res.lock().unwrap()[..], b"hello");
+    }
+
+    pub fn cancel(io: &IoService) {
+        let sv = Arc::new(TcpListener::new(io, Tcp::v4()).unwrap());
+        sv.bind(&TcpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+        sv.listen().unwrap();
+        let (rx, _tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+        let rx = Arc::new(rx);
+        let res = Arc::new(Mutex::new(Vec::new()));
+
+        let res_ = res.clone();
+        sv.async_accept(wrap(move |_: Arc<TcpListener>, r: io::Result<(TcpSocket, TcpEndpoint)>| {
+            res_.lock().unwrap().push(r.err().and_then(|err| err.raw_os_error()));
+        }, &sv));
+        let res_ = res.clone();
+        let buf = Arc::new(Mutex::new([0; 16]));
+        rx.async_read_some(&mut *buf.lock().unwrap(), wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+            res_.lock().unwrap().push(r.unwrap_err().raw_os_error());
+        }, &rx));
+        let sv_ = sv.clone();
+        let rx_ = rx.clone();
+        io.post(move |_| {
+            sv_.cancel();
+            rx_.cancel();
+        });
+
+        let now = Instant::now();
+        io.run();
+        assert!(now.elapsed() < Duration::from_secs(5));
+        assert_eq!(*res.lock().unwrap(), vec![Some(libc::ECANCELED), Some(libc::ECANCELED)]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+use std::sync::atomic::{Ordering, AtomicUsize};
+
+/// The number of buckets of the handler queue latency histogram.
+pub const LATENCY_BUCKETS: usize = 8;
+
+/// A snapshot of the runtime statistics of `IoService`.
+///
+/// # Examples
+/// ```
+/// use asyncio::IoService;
+///
+/// let io = IoService::new();
+/// io.post(|_| {});
+/// assert_eq!(io.stats().queued_handlers, 1);
+/// io.run();
+///
+/// let stats = io.stats();
+/// assert_eq!(stats.queued_handlers, 0);
+/// assert_eq!(stats.handlers_executed, 1);
+/// assert_eq!(stats.queue_latency.iter().sum::<usize>(), 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IoServiceStats {
+    /// The number of handlers that are waiting to be executed.
+    pub queued_handlers: usize,
+
+    /// The number of outstanding works (e.g. `IoServiceWork`).
+    pub outstanding_work: usize,
+
+    /// The number of threads that are running the `IoService`.
+    pub running_threads: usize,
+
+    /// The number of file descriptors that are registered to the reactor.
+    pub registered_entries: usize,
+
+    /// The number of pending input operations in the reactor.
+    pub pending_input_ops: usize,
+
+    /// The number of pending output operations in the reactor.
+    pub pending_output_ops: usize,
+
+    /// The number of pending timers in the timer queue.
+    pub pending_timers: usize,
+
+    /// The cumulative number of executed handlers.
+    pub handlers_executed: usize,
+
+    /// The cumulative number of the reactor wakeups that returned any events.
+    pub reactor_wakeups: usize,
+
+    /// The histogram of the time between posting and executing a handler.
+    ///
+    /// The `i`-th bucket counts the handlers that waited less than 10<sup>i</sup> microseconds,
+    /// and the last bucket counts the handlers that waited 1 second or more.
+    pub queue_latency: [usize; LATENCY_BUCKETS],
+}
+
+#[derive(Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicUsize; LATENCY_BUCKETS],
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, dur: Duration) {
+        let mut usec = dur.as_secs().saturating_mul(1000000) + (dur.subsec_nanos() / 1000) as u64;
+        let mut i = 0;
+        while usec > 0 && i < LATENCY_BUCKETS - 1 {
+            usec /= 10;
+            i += 1;
+        }
+        self.buckets[i].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> [usize; LATENCY_BUCKETS] {
+        let mut buckets = [0; LATENCY_BUCKETS];
+        for (dst, src) in buckets.iter_mut().zip(self.buckets.iter()) {
+            *dst = src.load(Ordering::Relaxed);
+        }
+        buckets
+    }
+}
+
+#[test]
+fn test_latency_histogram() {
+    let hist = LatencyHistogram::default();
+    hist.record(Duration::new(0, 0));
+    hist.record(Duration::new(0, 999));
+    hist.record(Duration::new(0, 1000));
+    hist.record(Duration::new(0, 9999));
+    hist.record(Duration::new(0, 10000));
+    hist.record(Duration::new(0, 999999999));
+    hist.record(Duration::new(1, 0));
+    hist.record(Duration::new(100, 0));
+    assert_eq!(hist.snapshot(), [2, 2, 1, 0, 0, 0, 1, 2]);
+}
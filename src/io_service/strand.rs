@@ -1,4 +1,5 @@
 use std::io;
+use std::thread;
 use std::boxed::FnBox;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
@@ -19,9 +20,29 @@ pub struct StrandData<T> {
     mutex: Arc<(Mutex<StrandQueue<T>>, UnsafeStrandCell<T>)>,
 }
 
+// ハンドラが panic した場合に Strand のロックを解放する.
+struct StrandGuard<'a, T: 'static> {
+    io: &'a IoService,
+    data: &'a StrandData<T>,
+}
+
+impl<'a, T: 'static> Drop for StrandGuard<'a, T> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let mut owner = self.data.mutex.0.lock().unwrap();
+            owner.locked = false;
+            if !owner.queue.is_empty() {
+                let data = self.data.clone();
+                self.io.post(move |io| data.dispatch(io, |_| {}));
+            }
+        }
+    }
+}
+
 impl<T> StrandData<T> {
     pub fn dispatch<F>(&self, io: &IoService, func: F)
-        where F: FnOnce(Strand<T>) + Send + 'static
+        where F: FnOnce(Strand<T>) + Send + 'static,
+              T: 'static,
     {
         {
             let mut owner = self.mutex.0.lock().unwrap();
@@ -34,6 +55,7 @@ impl<T> StrandData<T> {
             owner.locked = true;
         }
 
+        let _guard = StrandGuard { io: io, data: self };
         func(Strand { io: io, data: self });
 
         while let Some(func) = {
@@ -206,7 +228,7 @@ impl<T, F, R> Handler<R> for StrandHandler<T, F, R>
     fn wrap<G>(self, callback: G) -> Callback
         where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
     {
-        Box::new(move |io: *const IoService, ec| {
+        Callback::new(move |io: *const IoService, ec| {
             let io = unsafe { &*io };
             let StrandHandler { data, handler, _marker } = self;
             data.dispatch(io, move |st| {
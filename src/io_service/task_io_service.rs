@@ -1,46 +1,313 @@
 use std::fmt;
-use std::boxed::FnBox;
+use std::cmp;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::any::Any;
+use std::cell::{Cell, RefCell, UnsafeCell};
 use std::sync::{Mutex, Condvar};
-use std::sync::atomic::{Ordering, AtomicBool, AtomicUsize};
+use std::sync::atomic::{Ordering, AtomicBool, AtomicPtr, AtomicUsize};
 use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 use unsafe_cell::{UnsafeRefCell};
-use error::{READY, ECANCELED};
-use super::{IoService, Reactor, TimerQueue, Control, CallStack, ThreadInfo};
+use error::{ErrCode, READY, ECANCELED};
+use clock::Expiry;
+use super::{IoService, Callback, IoServiceStats, LatencyHistogram, PanicPolicy, Reactor, ReactorCore, TimerQueue, Control, CallStack, ThreadInfo};
 
-type Callback = Box<FnBox(*const IoService) + Send + 'static>;
+// ワーカー毎の実行キューの長さ. 溢れたハンドラは共有のキューに積む.
+const RUNQ_SIZE: usize = 256;
+
+// 実行キューを持てるワーカーの数. これを超えたスレッドは共有のキューだけを使う.
+const MAX_WORKERS: usize = 64;
+
+// 自スレッドの実行キューが積まれ続けても共有のキューが飢えないよう、この回数毎に共有のキューを先に見る.
+const INJECTOR_INTERVAL: usize = 61;
+
+// リアクタを呼び出しているスレッドなら true. リーダー自身が積んだハンドラでは割り込まない.
+thread_local!(static LEADER: Cell<bool> = Cell::new(false));
+
+// IoService を実行中のスレッドの状態. owner は実行中の IoServiceImpl のアドレスで、一致しなければ使わない.
+thread_local!(static LOCAL: RefCell<Local> = RefCell::new(Local::new(0, None)));
+
+struct Local {
+    owner: usize,
+    worker: Option<usize>,
+    deferred: VecDeque<Task>,
+    tick: usize,
+}
+
+impl Local {
+    fn new(owner: usize, worker: Option<usize>) -> Local {
+        Local {
+            owner: owner,
+            worker: worker,
+            deferred: VecDeque::new(),
+            tick: 0,
+        }
+    }
+}
+
+enum Task {
+    Callback(Callback, ErrCode, Instant),
+    EventLoop,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Wait {
+    NonBlock,
+    Block,
+    Until(Instant),
+}
+
+// 満了時刻までの残り時間をミリ秒で返す (切り上げ).
+fn timeout_msec(expiry: Instant) -> i32 {
+    let now = Instant::now();
+    if expiry <= now {
+        return 0;
+    }
+    let dur = expiry - now;
+    let msec = dur.as_secs().saturating_mul(1000) + ((dur.subsec_nanos() + 999999) / 1000000) as u64;
+    cmp::min(msec, i32::max_value() as u64) as i32
+}
+
+// 所有するスレッドだけが末尾に積み、所有者と他のスレッドが先頭から CAS で取り出す有界のリングバッファ.
+// 所有者も先頭から取り出すので、リーダーが積み直した EventLoop が先に積まれたハンドラを追い越さない.
+// Task はスロットに直接置くので、積むたびにヒープを確保しない.
+struct RunQueue {
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    buf: Vec<UnsafeCell<MaybeUninit<Task>>>,
+}
+
+unsafe impl Send for RunQueue {
+}
+
+unsafe impl Sync for RunQueue {
+}
+
+impl RunQueue {
+    fn new() -> RunQueue {
+        RunQueue {
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            buf: (0..RUNQ_SIZE).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+        }
+    }
+
+    // 所有するスレッドからのみ呼び出す. 満杯なら task を返す.
+    fn push(&self, task: Task) -> Result<(), Task> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= RUNQ_SIZE {
+            return Err(task);
+        }
+        // head を取り出し済みの位置まで読んでいるので、上書きするスロットを読んだ他のスレッドの CAS は必ず失敗する.
+        unsafe { ptr::write(self.buf[tail % RUNQ_SIZE].get(), MaybeUninit::new(task)) };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    fn pop(&self) -> Option<Task> {
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            if self.tail.load(Ordering::Acquire) == head {
+                return None;
+            }
+            // CAS の前に読んだ値は所有者に上書きされているかもしれないので、CAS に成功したときだけ使う.
+            // 失敗したときは MaybeUninit のまま捨てるので drop されない.
+            let task = unsafe { ptr::read_volatile(self.buf[head % RUNQ_SIZE].get()) };
+            match self.head.compare_exchange_weak(head, head.wrapping_add(1), Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Some(unsafe { task.assume_init() }),
+                Err(cur) => head = cur,
+            }
+        }
+    }
+}
+
+impl Drop for RunQueue {
+    fn drop(&mut self) {
+        while let Some(_) = self.pop() {
+        }
+    }
+}
+
+struct Worker {
+    owned: AtomicBool,
+    runq: AtomicPtr<RunQueue>,
+}
 
 pub struct IoServiceImpl {
-    mutex: Mutex<VecDeque<Callback>>,
+    workers: Vec<Worker>,
+    injector: Mutex<VecDeque<Task>>,
+    queued: AtomicUsize,
+    deferred: AtomicUsize,
+    event_queued: AtomicBool,
+    mutex: Mutex<()>,
     condvar: Condvar,
+    sleepers: AtomicUsize,
+    blocking: AtomicBool,
     stopped: AtomicBool,
     outstanding_work: AtomicUsize,
     nthreads: AtomicUsize,
-    pub react: Reactor,
+    executed: AtomicUsize,
+    latency: LatencyHistogram,
+    panic_policy: Mutex<PanicPolicy>,
+    drain: Mutex<Option<Instant>>,
+    pub react: ReactorCore,
     pub queue: TimerQueue,
     pub ctrl: Control,
 }
 
 impl IoServiceImpl {
-    pub fn new() -> IoServiceImpl {
+    pub fn new(react: Box<Reactor>) -> IoServiceImpl {
         IoServiceImpl {
-            mutex: Mutex::new(VecDeque::new()),
+            workers: (0..MAX_WORKERS).map(|_| Worker {
+                owned: AtomicBool::new(false),
+                runq: AtomicPtr::new(ptr::null_mut()),
+            }).collect(),
+            injector: Mutex::new(VecDeque::new()),
+            queued: AtomicUsize::new(0),
+            deferred: AtomicUsize::new(0),
+            event_queued: AtomicBool::new(false),
+            mutex: Mutex::new(()),
             condvar: Condvar::new(),
+            sleepers: AtomicUsize::new(0),
+            blocking: AtomicBool::new(false),
             stopped: AtomicBool::new(false),
             outstanding_work: AtomicUsize::new(0),
             nthreads: AtomicUsize::new(0),
-            react: Reactor::new(),
+            executed: AtomicUsize::new(0),
+            latency: LatencyHistogram::default(),
+            panic_policy: Mutex::new(PanicPolicy::Propagate),
+            drain: Mutex::new(None),
+            react: ReactorCore::new(react),
             queue: TimerQueue::new(),
             ctrl: Control::new(),
         }
     }
 
+    // タイマーの満了をバックエンドが通知できなければ、Control で通知する.
+    pub fn reset_timeout(&self, expiry: Expiry) {
+        if !self.react.reset_timeout(expiry) {
+            self.ctrl.reset_timeout(expiry);
+        }
+    }
+
     pub fn running_in_this_thread(&self) -> bool {
         CallStack::contains()
     }
 
+    fn id(&self) -> usize {
+        self as *const _ as usize
+    }
+
+    // 実行待ちのハンドラの数. defer されてスレッド固有のキューにあるハンドラも含む.
     fn count(&self) -> usize {
-        let task = self.mutex.lock().unwrap();
-        task.len()
+        self.queued.load(Ordering::SeqCst) + self.deferred.load(Ordering::SeqCst)
+    }
+
+    fn runq(&self, i: usize) -> Option<&RunQueue> {
+        let runq = self.workers[i].runq.load(Ordering::Acquire);
+        if runq.is_null() {
+            None
+        } else {
+            Some(unsafe { &*runq })
+        }
+    }
+
+    // 空いているワーカーの実行キューを確保する. 実行キューは IoServiceImpl が破棄されるまで解放しない.
+    fn claim(&self) -> Option<usize> {
+        for (i, worker) in self.workers.iter().enumerate() {
+            if !worker.owned.load(Ordering::Relaxed)
+                && worker.owned.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+            {
+                if worker.runq.load(Ordering::Acquire).is_null() {
+                    worker.runq.store(Box::into_raw(Box::new(RunQueue::new())), Ordering::Release);
+                }
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    // 自スレッドの実行キューに積み、ワーカーでなければ共有のキューに積む.
+    // 待機中のスレッドを起こすかどうかは呼び出し側が決める.
+    fn push(&self, task: Task) {
+        let id = self.id();
+        let worker = LOCAL.with(|local| {
+            let local = local.borrow();
+            if local.owner == id { local.worker } else { None }
+        });
+        // 取り出した側が先に減らして queued が負にならないよう、積む前に増やす.
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let res = match worker {
+            Some(i) => self.runq(i).unwrap().push(task),
+            None => Err(task),
+        };
+        if let Err(task) = res {
+            self.injector.lock().unwrap().push_back(task);
+        }
+    }
+
+    // 共有のキューに積む. EventLoop はどのスレッドが取り出してもよいので、先に積まれたハンドラの後ろに並べる.
+    fn push_shared(&self, task: Task) {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        self.injector.lock().unwrap().push_back(task);
+    }
+
+    // defer されたハンドラ、自スレッドの実行キュー、共有のキューの順に取り出し、全て空なら他のワーカーから盗む.
+    fn pop(&self) -> Option<Task> {
+        let id = self.id();
+        let (worker, tick, task) = LOCAL.with(|local| {
+            let mut local = local.borrow_mut();
+            if local.owner != id {
+                return (None, 0, None);
+            }
+            local.tick = local.tick.wrapping_add(1);
+            (local.worker, local.tick, local.deferred.pop_front())
+        });
+        if task.is_some() {
+            self.deferred.fetch_sub(1, Ordering::SeqCst);
+            return task;
+        }
+        let local = || worker.and_then(|i| self.runq(i).unwrap().pop());
+        let task = if tick % INJECTOR_INTERVAL == 0 {
+            self.pop_injector().or_else(local)
+        } else {
+            local().or_else(|| self.pop_injector())
+        };
+        let task = task.or_else(|| self.steal(worker, tick));
+        if task.is_some() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+        }
+        task
+    }
+
+    fn pop_injector(&self) -> Option<Task> {
+        self.injector.lock().unwrap().pop_front()
+    }
+
+    fn steal(&self, worker: Option<usize>, start: usize) -> Option<Task> {
+        for i in 0..MAX_WORKERS {
+            let i = (start + i) % MAX_WORKERS;
+            if Some(i) != worker {
+                if let Some(task) = self.runq(i).and_then(|runq| runq.pop()) {
+                    return Some(task);
+                }
+            }
+        }
+        None
+    }
+
+    // 待機中のスレッドを1つ起こす. 誰も待機していなければ、リアクタで待っているリーダーに割り込む.
+    fn notify_one(&self) {
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            let _lock = self.mutex.lock().unwrap();
+            self.condvar.notify_one();
+        } else if self.blocking.load(Ordering::SeqCst) && !LEADER.with(|leader| leader.get()) {
+            self.ctrl.interrupt();
+        }
     }
 
     pub fn stopped(&self) -> bool {
@@ -49,16 +316,38 @@ impl IoServiceImpl {
 
     pub fn stop(&self) {
         if !self.stopped.swap(true, Ordering::SeqCst) {
-            let mut _task = self.mutex.lock().unwrap();
+            let _lock = self.mutex.lock().unwrap();
             self.ctrl.interrupt();
             self.condvar.notify_all();
         }
     }
 
     pub fn reset(&self) {
+        *self.drain.lock().unwrap() = None;
         self.stopped.store(false, Ordering::SeqCst);
     }
 
+    pub fn graceful_stop(&self, io: &IoService, timeout: Duration) {
+        {
+            let mut drain = self.drain.lock().unwrap();
+            if drain.is_some() || self.stopped() {
+                return;
+            }
+            *drain = Some(Instant::now() + timeout);
+        }
+        self.react.cancel_accept(io);
+        self.ctrl.interrupt();
+    }
+
+    pub fn draining(&self) -> bool {
+        self.drain.lock().unwrap().is_some() && !self.stopped()
+    }
+
+    // 実行待ちのハンドラと書き込みが無くなるか、期限が過ぎたら true を返す.
+    fn drained(&self, expiry: Instant) -> bool {
+        expiry <= Instant::now() || (self.count() == 0 && self.react.stats().2 == 0)
+    }
+
     pub fn dispatch<F>(&self, io: &IoService, func: F)
         where F: FnOnce(&IoService) + Send + 'static
     {
@@ -72,22 +361,66 @@ impl IoServiceImpl {
     pub fn post<F>(&self, func: F)
         where F: FnOnce(&IoService) + Send + 'static
     {
-        let mut task = self.mutex.lock().unwrap();
-        task.push_back(Box::new(move |io: *const IoService| func(unsafe { &*io })));
-        self.condvar.notify_one();
+        self.post_callback(Callback::new(move |io: *const IoService, _| func(unsafe { &*io })), READY);
+    }
+
+    // 完了した操作のハンドラを、クロージャで包み直さずにそのまま積む.
+    pub fn post_callback(&self, callback: Callback, ec: ErrCode) {
+        self.push(Task::Callback(callback, ec, Instant::now()));
+        self.notify_one();
     }
 
-    fn wait(&self) -> Option<Callback> {
-        let mut task = self.mutex.lock().unwrap();
+    pub fn defer<F>(&self, func: F)
+        where F: FnOnce(&IoService) + Send + 'static
+    {
+        let id = self.id();
+        let func = LOCAL.with(move |local| {
+            let mut local = local.borrow_mut();
+            if local.owner == id {
+                // 現在のハンドラの継続なので、他のスレッドは起こさずに自スレッドで実行する.
+                local.deferred.push_back(Task::Callback(Callback::new(move |io: *const IoService, _| func(unsafe { &*io })), READY, Instant::now()));
+                None
+            } else {
+                Some(func)
+            }
+        });
+        match func {
+            None => { self.deferred.fetch_add(1, Ordering::SeqCst); },
+            Some(func) => self.post(func),
+        }
+    }
+
+    fn wait(&self, mode: Wait) -> Option<Task> {
         loop {
+            let now = Instant::now();
+            if let Wait::Until(expiry) = mode {
+                if expiry <= now {
+                    return None;
+                }
+            }
+            if let Some(t) = self.pop() {
+                return Some(t);
+            }
+
+            let lock = self.mutex.lock().unwrap();
             let stoppable = self.outstanding_work.load(Ordering::Relaxed) == 0
                 || self.stopped.load(Ordering::Relaxed);
-            if let Some(callback) = task.pop_front() {
-                return Some(callback);
-            } else if stoppable {
-                return None
+            // 自スレッドの defer されたハンドラは pop で取り出し済みなので、共有されたハンドラだけを見る.
+            if self.queued.load(Ordering::SeqCst) > 0 {
+                continue;
+            } else if stoppable || mode == Wait::NonBlock {
+                return None;
+            }
+
+            // 積んだ側は queued を増やしてから sleepers を見るので、逆順で確認すれば起こし損ねない.
+            self.sleepers.fetch_add(1, Ordering::SeqCst);
+            if self.queued.load(Ordering::SeqCst) == 0 {
+                match mode {
+                    Wait::Until(expiry) => drop(self.condvar.wait_timeout(lock, expiry - now).unwrap()),
+                    _ => drop(self.condvar.wait(lock).unwrap()),
+                }
             }
-            task = self.condvar.wait(task).unwrap();
+            self.sleepers.fetch_sub(1, Ordering::SeqCst);
         }
     }
 
@@ -97,41 +430,179 @@ impl IoServiceImpl {
             io.0.queue.cancel_all(io);
             io.0.ctrl.stop(io);
         } else {
-            io.post(move |io| {
-                let mut count = io.0.outstanding_work.load(Ordering::Relaxed);
-                let timeout = if count > 0 && io.0.nthreads.load(Ordering::Relaxed) > 1 {
-                    Some(io.0.ctrl.wait_duration(200000))
-                } else {
-                    None
-                };
-                count += io.0.react.poll(timeout, io);
-                count += io.0.queue.ready_expired(io);
-                if count == 0 && io.0.count() == 0 {
-                    io.0.stop();
-                }
-                Self::event_loop(io);
-            });
+            io.0.event_queued.store(true, Ordering::Relaxed);
+            io.0.push_shared(Task::EventLoop);
+            io.0.notify_one();
+        }
+    }
+
+    // EventLoop を取り出したスレッドがリーダーとなり、実行待ちのハンドラが無ければリアクタで待つ.
+    // 他のスレッドは condvar で待ち、新しいハンドラが積まれるとリーダーは Control で起こされる.
+    fn event_poll(io: &IoService, mode: Wait) {
+        let drain = *io.0.drain.lock().unwrap();
+        let mut count = io.0.outstanding_work.load(Ordering::Relaxed);
+        let pending = count + io.0.react.pending() + io.0.queue.len();
+        // 積んだ側は queued を増やしてから blocking を見るので、逆順で確認すれば起こし損ねない.
+        io.0.blocking.store(pending > 0 && mode != Wait::NonBlock, Ordering::SeqCst);
+        let timeout = if io.0.blocking.load(Ordering::SeqCst) && io.0.queued.load(Ordering::SeqCst) == 0 {
+            match (mode, drain) {
+                (Wait::NonBlock, _) => None,
+                (Wait::Block, None) => Some(io.0.ctrl.wait_duration(-1)),
+                (Wait::Block, Some(expiry)) => Some(io.0.ctrl.wait_duration(timeout_msec(expiry))),
+                (Wait::Until(expiry), drain) => {
+                    let expiry = drain.map_or(expiry, |drain| cmp::min(expiry, drain));
+                    Some(io.0.ctrl.wait_duration(timeout_msec(expiry)))
+                },
+            }
+        } else {
+            None
+        };
+        LEADER.with(|leader| leader.set(true));
+        count += io.0.react.poll(timeout, io);
+        LEADER.with(|leader| leader.set(false));
+        io.0.blocking.store(false, Ordering::SeqCst);
+        count += io.0.queue.ready_expired(io);
+        if count == 0 && io.0.count() == 0 {
+            io.0.stop();
+        } else if let Some(expiry) = drain {
+            if io.0.drained(expiry) {
+                io.0.stop();
+            }
+        }
+        Self::event_loop(io);
+    }
+
+    // 1つのハンドラを実行したら 1 を返す.
+    // NonBlock の場合、イベントループは高々1回だけ実行する.
+    fn do_run_one(&self, io: &IoService, mode: Wait) -> usize {
+        let mut polled = false;
+        while let Some(t) = self.wait(mode) {
+            match t {
+                Task::Callback(func, ec, posted) => {
+                    self.latency.record(posted.elapsed());
+                    let res = panic::catch_unwind(AssertUnwindSafe(|| func.call(io, ec)));
+                    self.executed.fetch_add(1, Ordering::Relaxed);
+                    if let Err(err) = res {
+                        self.panicked(io, err);
+                    }
+                    return 1;
+                },
+                Task::EventLoop if mode == Wait::NonBlock && polled => {
+                    self.event_queued.store(false, Ordering::Relaxed);
+                    Self::event_loop(io);
+                    return 0;
+                },
+                Task::EventLoop => {
+                    self.event_queued.store(false, Ordering::Relaxed);
+                    polled = true;
+                    Self::event_poll(io, mode);
+                },
+            }
         }
+        0
     }
 
-    pub fn run(&self, io: &IoService) {
+    fn running<F>(&self, io: &IoService, func: F) -> usize
+        where F: FnOnce() -> usize
+    {
         if self.stopped() {
-            return;
+            return 0;
         }
 
-        let thread_info = match ThreadInfo::new() {
-            None => return,
+        let _thread_info = match ThreadInfo::new() {
+            None => return 0,
             Some(thread_info) => thread_info,
         };
 
         self.nthreads.fetch_add(1, Ordering::SeqCst);
+        let worker = self.claim();
+        let outer = LOCAL.with(|local| mem::replace(&mut *local.borrow_mut(), Local::new(self.id(), worker)));
         if self.ctrl.start(io) {
+            // 停止中に追加されたタイマーは Control に設定されていない.
+            if let Some(expiry) = self.queue.first_expiry() {
+                self.reset_timeout(expiry);
+            }
             Self::event_loop(io);
         }
-        while let Some(func) = self.wait() {
-            func(io);
-        }
+        let res = panic::catch_unwind(AssertUnwindSafe(func));
         self.nthreads.fetch_sub(1, Ordering::SeqCst);
+        let local = LOCAL.with(|local| mem::replace(&mut *local.borrow_mut(), outer));
+        if let Some(i) = worker {
+            // 実行キューに残ったハンドラは他のスレッドが盗むか、次にこのワーカーを確保したスレッドが実行する.
+            self.workers[i].owned.store(false, Ordering::Release);
+        }
+        // defer されたハンドラが残っていれば他のスレッドに引き継ぐ.
+        let n = local.deferred.len();
+        for task in local.deferred {
+            self.push_shared(task);
+        }
+        self.deferred.fetch_sub(n, Ordering::SeqCst);
+        if self.queued.load(Ordering::SeqCst) > 0 {
+            self.notify_one();
+        }
+        match res {
+            Ok(n) => n,
+            Err(err) => panic::resume_unwind(err),
+        }
+    }
+
+    fn panicked(&self, io: &IoService, err: Box<Any + Send>) {
+        let policy = self.panic_policy.lock().unwrap().clone();
+        match policy {
+            PanicPolicy::Propagate => panic::resume_unwind(err),
+            PanicPolicy::Abort => process::abort(),
+            PanicPolicy::Catch(hook) => hook(io, err),
+        }
+    }
+
+    pub fn set_panic_policy(&self, policy: PanicPolicy) {
+        *self.panic_policy.lock().unwrap() = policy;
+    }
+
+    fn run_all(&self, io: &IoService, mode: Wait) -> usize {
+        self.running(io, || {
+            let mut n = 0;
+            while self.do_run_one(io, mode) > 0 {
+                n += 1;
+            }
+            n
+        })
+    }
+
+    pub fn run(&self, io: &IoService) -> usize {
+        self.run_all(io, Wait::Block)
+    }
+
+    pub fn run_one(&self, io: &IoService) -> usize {
+        self.running(io, || self.do_run_one(io, Wait::Block))
+    }
+
+    pub fn run_until(&self, io: &IoService, expiry: Instant) -> usize {
+        self.run_all(io, Wait::Until(expiry))
+    }
+
+    pub fn poll(&self, io: &IoService) -> usize {
+        self.run_all(io, Wait::NonBlock)
+    }
+
+    pub fn poll_one(&self, io: &IoService) -> usize {
+        self.running(io, || self.do_run_one(io, Wait::NonBlock))
+    }
+
+    pub fn stats(&self) -> IoServiceStats {
+        let (registered_entries, pending_input_ops, pending_output_ops) = self.react.stats();
+        IoServiceStats {
+            queued_handlers: self.count().saturating_sub(self.event_queued.load(Ordering::Relaxed) as usize),
+            outstanding_work: self.outstanding_work.load(Ordering::Relaxed),
+            running_threads: self.nthreads.load(Ordering::Relaxed),
+            registered_entries: registered_entries,
+            pending_input_ops: pending_input_ops,
+            pending_output_ops: pending_output_ops,
+            pending_timers: self.queue.len(),
+            handlers_executed: self.executed.load(Ordering::Relaxed),
+            reactor_wakeups: self.react.wakeups(),
+            queue_latency: self.latency.snapshot(),
+        }
     }
 
     pub fn work_started(&self) {
@@ -143,6 +614,17 @@ impl IoServiceImpl {
     }
 }
 
+impl Drop for IoServiceImpl {
+    fn drop(&mut self) {
+        for worker in &self.workers {
+            let runq = worker.runq.load(Ordering::Acquire);
+            if !runq.is_null() {
+                drop(unsafe { Box::from_raw(runq) });
+            }
+        }
+    }
+}
+
 impl fmt::Debug for IoServiceImpl {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "TaskIoService")
@@ -0,0 +1,191 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use error::{ErrCode, READY, ETIMEDOUT};
+use clock::{Clock, SteadyClock, Expiry};
+use super::{IoService, Callback, Handler, AsyncResult, CancelState, TimerActor};
+
+struct TimeoutData {
+    expiry: Expiry,
+    timer: Option<TimerActor>,
+    done: bool,
+}
+
+impl TimeoutData {
+    fn finish(&mut self) {
+        self.done = true;
+        if let Some(timer) = self.timer.take() {
+            // 満了していなければタイマーを取り消す. コールバックは呼ばずに破棄する.
+            drop(timer.unset_wait());
+        }
+    }
+}
+
+impl Drop for TimeoutData {
+    fn drop(&mut self) {
+        self.finish()
+    }
+}
+
+/// The handler that completes the operation with `ErrorKind::TimedOut` if it has not finished in time.
+pub struct TimeoutHandler<H> {
+    handler: H,
+    state: Arc<CancelState>,
+    data: Arc<Mutex<TimeoutData>>,
+}
+
+impl<H, R> Handler<R> for TimeoutHandler<H>
+    where H: Handler<R>,
+{
+    type Output = H::Output;
+
+    fn callback(self, io: &IoService, res: io::Result<R>) {
+        self.data.lock().unwrap().finish();
+        self.handler.callback(io, res)
+    }
+
+    fn wrap<G>(self, callback: G) -> Callback
+        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
+    {
+        let TimeoutHandler { handler, state, data } = self;
+        let state_ = state.clone();
+        let mut callback = handler.wrap(move |io, ec, handler| {
+            callback(io, ec, TimeoutHandler {
+                handler: handler,
+                state: state_,
+                data: data,
+            })
+        });
+        callback.set_cancel(state, 0);
+        callback
+    }
+
+    type AsyncResult = TimeoutAsyncResult<H::AsyncResult>;
+
+    fn async_result(&self) -> Self::AsyncResult {
+        TimeoutAsyncResult {
+            out: self.handler.async_result(),
+            state: self.state.clone(),
+            data: self.data.clone(),
+        }
+    }
+}
+
+pub struct TimeoutAsyncResult<A> {
+    out: A,
+    state: Arc<CancelState>,
+    data: Arc<Mutex<TimeoutData>>,
+}
+
+impl<A, R> AsyncResult<R> for TimeoutAsyncResult<A>
+    where A: AsyncResult<R>,
+{
+    fn get(self, io: &IoService) -> R {
+        // 操作が開始された後に呼ばれるので、ここでタイマーを開始する.
+        // コルーチン等は get の中で完了を待つので、その前に開始しておく.
+        {
+            let mut data = self.data.lock().unwrap();
+            if !data.done && data.timer.is_none() {
+                let timer = TimerActor::new(io);
+                let state = self.state;
+                timer.set_wait(data.expiry, Callback::new(move |_, ec| {
+                    if ec == READY {
+                        CancelState::cancel(&state, ETIMEDOUT);
+                    }
+                }));
+                data.timer = Some(timer);
+            }
+        }
+        self.out.get(io)
+    }
+}
+
+/// Sets the timeout to the asynchronous operation.
+///
+/// If the operation has not finished within `timeout`, it is withdrawn from the queue and
+/// completes with `ErrorKind::TimedOut`. The other operations on the same object are not cancelled.
+/// The timer is cancelled when the operation completes.
+///
+/// # Examples
+/// ```
+/// use std::io;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use asyncio::{IoService, Stream, wrap, with_timeout};
+/// use asyncio::local::{LocalStream, LocalStreamSocket, connect_pair};
+///
+/// let io = &IoService::new();
+/// let (soc, _peer): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+/// let soc = Arc::new(soc);
+///
+/// let mut buf = [0; 8];
+/// soc.async_read_some(&mut buf, with_timeout(Duration::from_millis(10), wrap(|_: Arc<LocalStreamSocket>, res: io::Result<usize>| {
+///     assert_eq!(res.unwrap_err().kind(), io::ErrorKind::TimedOut);
+/// }, &soc)));
+/// io.run();
+/// ```
+pub fn with_timeout<H>(timeout: Duration, handler: H) -> TimeoutHandler<H> {
+    TimeoutHandler {
+        handler: handler,
+        state: Arc::new(CancelState::default()),
+        data: Arc::new(Mutex::new(TimeoutData {
+            expiry: SteadyClock::expires_from(timeout),
+            timer: None,
+            done: false,
+        })),
+    }
+}
+
+#[cfg(test)]
+use std::time::Instant;
+#[cfg(test)]
+use super::wrap;
+#[cfg(test)]
+use local::{LocalStream, LocalStreamSocket, connect_pair};
+#[cfg(test)]
+use stream::Stream;
+
+#[cfg(test)]
+fn read_result(res: &Arc<Mutex<Option<io::Result<usize>>>>) -> Box<FnMut(Arc<LocalStreamSocket>, io::Result<usize>) + Send> {
+    let res = res.clone();
+    Box::new(move |_, r| *res.lock().unwrap() = Some(r))
+}
+
+#[test]
+fn test_timeout_one_op() {
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let rx = Arc::new(rx);
+    let res1 = Arc::new(Mutex::new(None));
+    let res2 = Arc::new(Mutex::new(None));
+
+    let mut buf1 = [0; 8];
+    let mut buf2 = [0; 8];
+    let res1_ = res1.clone();
+    rx.async_read_some(&mut buf1, with_timeout(Duration::from_millis(1), wrap(move |_: Arc<LocalStreamSocket>, r| {
+        *res1_.lock().unwrap() = Some(r);
+        tx.write_some(b"hello").unwrap();
+    }, &rx)));
+    rx.async_read_some(&mut buf2, wrap(read_result(&res2), &rx));
+    assert_eq!(io.stats().pending_input_ops, 2);
+
+    io.run();
+    assert_eq!(res1.lock().unwrap().take().unwrap().unwrap_err().kind(), io::ErrorKind::TimedOut);
+    assert_eq!(res2.lock().unwrap().take().unwrap().unwrap(), 5);
+}
+
+#[test]
+fn test_timeout_completed() {
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let rx = Arc::new(rx);
+    let res = Arc::new(Mutex::new(None));
+
+    tx.write_some(b"hello").unwrap();
+    let mut buf = [0; 8];
+    let now = Instant::now();
+    rx.async_read_some(&mut buf, with_timeout(Duration::new(60, 0), wrap(read_result(&res), &rx)));
+    io.run();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 5);
+    assert!(now.elapsed() < Duration::new(10, 0));
+}
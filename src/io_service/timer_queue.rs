@@ -83,7 +83,7 @@ fn find_timeout(queue: &Vec<EntryPtr>, expiry: Expiry) -> usize {
 fn drain(queue: &mut Vec<EntryPtr>, len: usize, io: &IoService, ec: ErrCode) {
     for ptr in queue.drain(..len) {
         let Op { expiry:_, callback } = unsafe { &mut *ptr.0 }.op.take().unwrap();
-        io.post(move |io| callback(io, ec));
+        io.0.post_callback(callback, ec);
     }
 }
 
@@ -131,6 +131,11 @@ impl TimerQueue {
         }
     }
 
+    pub fn len(&self) -> usize {
+        let queue = self.mutex.lock().unwrap();
+        queue.len()
+    }
+
     pub fn cancel_all(&self, io: &IoService) {
         let mut queue = self.mutex.lock().unwrap();
         let len = queue.len();
@@ -141,8 +146,20 @@ impl TimerQueue {
         let mut queue = self.mutex.lock().unwrap();
         let len = find_timeout(&queue, Expiry::now());
         drain(&mut queue, len, io, READY);
+        // 先頭が入れ替わったので、次の満了時刻でリアクタを起こすように設定し直す.
+        if len > 0 {
+            io.0.reset_timeout(match queue.first() {
+                Some(ptr) => unsafe { &*ptr.0 }.op.as_ref().unwrap().expiry,
+                None => Expiry::default(),
+            });
+        }
         queue.len()
     }
+
+    pub fn first_expiry(&self) -> Option<Expiry> {
+        let queue = self.mutex.lock().unwrap();
+        queue.first().map(|ptr| unsafe { &*ptr.0 }.op.as_ref().unwrap().expiry)
+    }
 }
 
 pub struct TimerActor {
@@ -162,10 +179,10 @@ impl TimerActor {
         let mut is_first = false;
         let op = Op { expiry: expiry, callback: callback };
         if let Some(callback) = self.io.0.queue.set(unsafe { self.ptr.get() }, op, &mut is_first) {
-            self.io.post(|io| callback(io, ECANCELED));
+            self.io.0.post_callback(callback, ECANCELED);
         }
         if is_first {
-            self.io.0.ctrl.reset_timeout(expiry)
+            self.io.0.reset_timeout(expiry)
         }
     }
 
@@ -173,7 +190,7 @@ impl TimerActor {
         let mut expiry_opt = None;
         let callback_opt = self.io.0.queue.unset(unsafe { self.ptr.get() }, &mut expiry_opt);
         if let Some(expiry) = expiry_opt {
-            self.io.0.ctrl.reset_timeout(expiry);
+            self.io.0.reset_timeout(expiry);
         }
         callback_opt
     }
@@ -189,6 +206,6 @@ unsafe impl IoObject for TimerActor {
 fn test_timer_set_unset() {
     let io = &IoService::new();
     let act = TimerActor::new(io);
-    act.set_wait(Expiry::now(), Box::new(|_,_| {}));
+    act.set_wait(Expiry::now(), Callback::new(|_,_| {}));
     assert!(act.unset_wait().is_some());
 }
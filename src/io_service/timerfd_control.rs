@@ -84,8 +84,9 @@ impl Control {
         }
     }
 
-    pub fn wait_duration(&self, _max: i32) -> i32 {
-        -1
+    // タイマーの満了は timerfd で通知されるため、max をそのまま返す.
+    pub fn wait_duration(&self, max: i32) -> i32 {
+        max
     }
 }
 
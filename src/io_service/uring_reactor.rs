@@ -0,0 +1,794 @@
+use std::io;
+use std::cmp;
+use std::mem;
+use std::ptr;
+use std::os::unix::io::RawFd;
+use std::sync::{Mutex};
+use std::sync::atomic::{Ordering, AtomicU32};
+use std::collections::{HashMap, VecDeque};
+use clock::Expiry;
+use super::{Reactor, ReactorEvent, ReactorOp};
+use libc::{EPOLLIN, EPOLLOUT, EPOLLERR, EPOLLHUP, EPOLLET, MAP_SHARED, MAP_POPULATE, PROT_READ, PROT_WRITE, MAP_FAILED, EFD_CLOEXEC,
+           EINTR, EAGAIN, EBUSY, ETIME, c_int, c_long, c_uint, c_void, sockaddr_storage, timespec, syscall, mmap, munmap, eventfd, close};
+
+const SYS_IO_URING_SETUP: c_long = 425;
+const SYS_IO_URING_ENTER: c_long = 426;
+const SYS_IO_URING_REGISTER: c_long = 427;
+
+const IORING_OFF_SQ_RING: i64 = 0;
+const IORING_OFF_CQ_RING: i64 = 0x8000000;
+const IORING_OFF_SQES: i64 = 0x10000000;
+
+const IORING_OP_READV: u8 = 1;
+const IORING_OP_WRITEV: u8 = 2;
+const IORING_OP_POLL_ADD: u8 = 6;
+const IORING_OP_POLL_REMOVE: u8 = 7;
+const IORING_OP_TIMEOUT: u8 = 11;
+const IORING_OP_TIMEOUT_REMOVE: u8 = 12;
+const IORING_OP_ACCEPT: u8 = 13;
+const IORING_OP_ASYNC_CANCEL: u8 = 14;
+const IORING_OP_CONNECT: u8 = 16;
+const IORING_OP_READ: u8 = 22;
+const IORING_OP_WRITE: u8 = 23;
+const IORING_POLL_ADD_MULTI: u32 = 1 << 0;
+const IORING_TIMEOUT_ABS: u32 = 1 << 0;
+const IORING_CQE_F_MORE: u32 = 1 << 1;
+const IORING_ENTER_GETEVENTS: c_uint = 1 << 0;
+const IORING_ENTER_EXT_ARG: c_uint = 1 << 3;
+const IORING_REGISTER_PROBE: c_uint = 8;
+const IO_URING_OP_SUPPORTED: u16 = 1 << 0;
+
+const IORING_FEAT_NODROP: u32 = 1 << 1;
+const IORING_FEAT_EXT_ARG: u32 = 1 << 8;
+
+const URING_ENTRIES: u32 = 256;
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct io_sqring_offsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct io_cqring_offsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct io_uring_params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: io_sqring_offsets,
+    cq_off: io_cqring_offsets,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct io_uring_sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    op_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad2: u64,
+}
+
+#[repr(C)]
+struct io_uring_cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+struct io_uring_probe_op {
+    op: u8,
+    resv: u8,
+    flags: u16,
+    resv2: u32,
+}
+
+#[repr(C)]
+struct io_uring_probe {
+    last_op: u8,
+    ops_len: u8,
+    resv: u16,
+    resv2: [u32; 3],
+    ops: [io_uring_probe_op; 64],
+}
+
+#[repr(C)]
+struct kernel_timespec {
+    tv_sec: i64,
+    tv_nsec: i64,
+}
+
+#[repr(C)]
+struct io_uring_getevents_arg {
+    sigmask: u64,
+    sigmask_sz: u32,
+    pad: u32,
+    ts: u64,
+}
+
+fn io_uring_enter(fd: RawFd, to_submit: u32, min_complete: u32, flags: c_uint, arg: *const c_void, argsz: usize) -> io::Result<u32> {
+    match unsafe { syscall(SYS_IO_URING_ENTER, fd, to_submit, min_complete, flags, arg, argsz) } {
+        n if n >= 0 => Ok(n as u32),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn unsupported(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("io_uring does not support {}", what))
+}
+
+fn opcode(op: &ReactorOp) -> u8 {
+    match *op {
+        ReactorOp::Read(..) => IORING_OP_READ,
+        ReactorOp::Write(..) => IORING_OP_WRITE,
+        ReactorOp::ReadV(..) => IORING_OP_READV,
+        ReactorOp::WriteV(..) => IORING_OP_WRITEV,
+        ReactorOp::Accept => IORING_OP_ACCEPT,
+        ReactorOp::Connect(..) => IORING_OP_CONNECT,
+    }
+}
+
+// 必要な機能をカーネルが持っているか調べて、投入できる操作の opcode の集合を返す.
+fn probe(uring_fd: RawFd, params: &io_uring_params) -> io::Result<u64> {
+    if (params.features & IORING_FEAT_NODROP) == 0 {
+        return Err(unsupported("IORING_FEAT_NODROP"));
+    }
+    if (params.features & IORING_FEAT_EXT_ARG) == 0 {
+        return Err(unsupported("IORING_ENTER_EXT_ARG"));
+    }
+    let mut probe: io_uring_probe = unsafe { mem::zeroed() };
+    let nr_ops = probe.ops.len() as c_uint;
+    libc_try!(syscall(SYS_IO_URING_REGISTER, uring_fd, IORING_REGISTER_PROBE, &mut probe, nr_ops));
+    let supported = |op: u8| {
+        op <= probe.last_op && (op as usize) < (probe.ops_len as usize)
+            && (probe.ops[op as usize].flags & IO_URING_OP_SUPPORTED) != 0
+    };
+    for &(op, name) in &[(IORING_OP_POLL_ADD, "IORING_OP_POLL_ADD"), (IORING_OP_POLL_REMOVE, "IORING_OP_POLL_REMOVE"),
+                         (IORING_OP_TIMEOUT, "IORING_OP_TIMEOUT"), (IORING_OP_TIMEOUT_REMOVE, "IORING_OP_TIMEOUT_REMOVE"),
+                         (IORING_OP_ASYNC_CANCEL, "IORING_OP_ASYNC_CANCEL")] {
+        if !supported(op) {
+            return Err(unsupported(name));
+        }
+    }
+    // 読み書きなどは、カーネルが対応している操作だけを投入し、それ以外は準備ができるのを待って実行する.
+    Ok([IORING_OP_READ, IORING_OP_WRITE, IORING_OP_READV, IORING_OP_WRITEV, IORING_OP_ACCEPT, IORING_OP_CONNECT].iter()
+       .filter(|&&op| supported(op))
+       .fold(0, |ops, &op| ops | (1 << op)))
+}
+
+struct Mmap {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(fd: RawFd, len: usize, offset: i64) -> io::Result<Mmap> {
+        let ptr = unsafe { mmap(ptr::null_mut(), len, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_POPULATE, fd, offset) };
+        if ptr == MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { ptr: ptr, len: len })
+    }
+
+    unsafe fn at<T>(&self, offset: u32) -> *mut T {
+        (self.ptr as *mut u8).offset(offset as isize) as *mut T
+    }
+}
+
+unsafe impl Send for Mmap {
+}
+
+unsafe impl Sync for Mmap {
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        libc_ign!(munmap(self.ptr, self.len));
+    }
+}
+
+// 完了を待っている操作. カーネルが読むアドレスや時刻は、完了するまで保持する.
+#[allow(dead_code)]
+enum Pending {
+    Op(usize, bool, Option<Box<sockaddr_storage>>),
+    Timeout(Box<kernel_timespec>),
+}
+
+struct UringData {
+    polling_entry: HashMap<u64, (usize, RawFd, u32)>,
+    polling_token: HashMap<usize, u64>,
+    pending: HashMap<u64, Pending>,
+    pending_op: HashMap<(usize, bool), u64>,
+    timeout: Option<u64>,
+    next_id: u64,
+    backlog: VecDeque<io_uring_sqe>,
+    sq_ring: Mmap,
+    sqes: Mmap,
+    sq_params: io_sqring_offsets,
+}
+
+impl UringData {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    // SQ が一杯のときに備えて、いったん backlog に積む.
+    fn push(&mut self, sqe: io_uring_sqe) {
+        self.backlog.push_back(sqe);
+    }
+
+    // SQ の空きに backlog を詰めて、未提出のエントリ数を返す.
+    fn fill(&mut self) -> u32 {
+        unsafe {
+            let head = (*self.sq_ring.at::<AtomicU32>(self.sq_params.head)).load(Ordering::Acquire);
+            let tail_ptr = self.sq_ring.at::<AtomicU32>(self.sq_params.tail);
+            let mask = *self.sq_ring.at::<u32>(self.sq_params.ring_mask);
+            let entries = *self.sq_ring.at::<u32>(self.sq_params.ring_entries);
+            let array = self.sq_ring.at::<u32>(self.sq_params.array);
+            let mut tail = (*tail_ptr).load(Ordering::Relaxed);
+            while tail.wrapping_sub(head) < entries {
+                let sqe = match self.backlog.pop_front() {
+                    Some(sqe) => sqe,
+                    None => break,
+                };
+                ptr::write(self.sqes.at::<io_uring_sqe>(0).offset((tail & mask) as isize), sqe);
+                *array.offset((tail & mask) as isize) = tail & mask;
+                tail = tail.wrapping_add(1);
+            }
+            (*tail_ptr).store(tail, Ordering::Release);
+            tail.wrapping_sub(head)
+        }
+    }
+
+    // 積まれたエントリを提出する. カーネルが受け付けない分 (EBUSY 等) は次の提出で再送する.
+    fn submit(&mut self, uring_fd: RawFd) {
+        loop {
+            let pending = self.fill();
+            if pending == 0 {
+                return;
+            }
+            match io_uring_enter(uring_fd, pending, 0, 0, ptr::null(), 0) {
+                Ok(0) => return,
+                Ok(n) => if n == pending && self.backlog.is_empty() {
+                    return;
+                },
+                Err(err) => match err.raw_os_error() {
+                    Some(EINTR) => (),
+                    Some(EAGAIN) | Some(EBUSY) => return,
+                    _ => panic!("{}", err),
+                },
+            }
+        }
+    }
+
+    fn poll_add(&mut self, id: u64, fd: RawFd, events: u32) {
+        self.push(io_uring_sqe {
+            opcode: IORING_OP_POLL_ADD,
+            fd: fd,
+            len: IORING_POLL_ADD_MULTI,
+            op_flags: events,
+            user_data: id,
+            .. io_uring_sqe::default()
+        });
+    }
+
+    fn poll_remove(&mut self, id: u64) {
+        self.push(io_uring_sqe {
+            opcode: IORING_OP_POLL_REMOVE,
+            fd: -1,
+            addr: id,
+            user_data: 0,
+            .. io_uring_sqe::default()
+        });
+    }
+
+    // 読み書きは記述子の現在の位置から行う.
+    fn rw(&mut self, id: u64, opcode: u8, fd: RawFd, addr: u64, len: usize) {
+        self.push(io_uring_sqe {
+            opcode: opcode,
+            fd: fd,
+            off: !0,
+            addr: addr,
+            len: cmp::min(len, i32::max_value() as usize) as u32,
+            user_data: id,
+            .. io_uring_sqe::default()
+        });
+    }
+
+    fn cancel(&mut self, opcode: u8, id: u64) {
+        self.push(io_uring_sqe {
+            opcode: opcode,
+            fd: -1,
+            addr: id,
+            user_data: 0,
+            .. io_uring_sqe::default()
+        });
+    }
+}
+
+/// The `Reactor` backend using io_uring(7).
+///
+/// The reads, the writes, the accepts and the connects are submitted to the submission queue
+/// (`IORING_OP_READ`, `IORING_OP_WRITE`, `IORING_OP_READV`, `IORING_OP_WRITEV`, `IORING_OP_ACCEPT`
+/// and `IORING_OP_CONNECT`), and their handlers are completed from the completion queue.
+/// The expiry of the timers is also submitted as `IORING_OP_TIMEOUT`.
+/// The other operations (e.g. `recvmsg` or `sendfile`) wait the readiness that is watched by the multishot
+/// `IORING_OP_POLL_ADD`, and are performed by the system calls as with the other backends.
+///
+/// It requires Linux 5.13 or later. `IoService::new()` falls back to the epoll (or poll) backend
+/// if io_uring is not available.
+pub struct UringReactor {
+    uring_fd: RawFd,
+    ops: u64,
+    mutex: Mutex<UringData>,
+    cq_ring: Mmap,
+    cq_params: io_cqring_offsets,
+}
+
+impl UringReactor {
+    /// Returns a new `UringReactor`.
+    ///
+    /// # Panics
+    /// Panics if io_uring is not available.
+    pub fn new() -> UringReactor {
+        match Self::try_new() {
+            Ok(react) => react,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Returns a new `UringReactor`, or an error if io_uring or the required features are not available.
+    pub fn try_new() -> io::Result<UringReactor> {
+        let mut params = io_uring_params::default();
+        let uring_fd = libc_try!(syscall(SYS_IO_URING_SETUP, URING_ENTRIES, &mut params) as c_int);
+        let react = match Self::setup(uring_fd, &params) {
+            Ok((ops, data, cq_ring)) => UringReactor {
+                uring_fd: uring_fd,
+                ops: ops,
+                mutex: Mutex::new(data),
+                cq_ring: cq_ring,
+                cq_params: params.cq_off,
+            },
+            Err(err) => {
+                libc_ign!(close(uring_fd));
+                return Err(err);
+            },
+        };
+        try!(react.probe_multishot());
+        Ok(react)
+    }
+
+    fn setup(uring_fd: RawFd, params: &io_uring_params) -> io::Result<(u64, UringData, Mmap)> {
+        let ops = try!(probe(uring_fd, params));
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * mem::size_of::<u32>();
+        let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * mem::size_of::<io_uring_cqe>();
+        let sqes_len = params.sq_entries as usize * mem::size_of::<io_uring_sqe>();
+        let data = UringData {
+            polling_entry: HashMap::new(),
+            polling_token: HashMap::new(),
+            pending: HashMap::new(),
+            pending_op: HashMap::new(),
+            timeout: None,
+            next_id: 0,
+            backlog: VecDeque::new(),
+            sq_ring: try!(Mmap::new(uring_fd, sq_len, IORING_OFF_SQ_RING)),
+            sqes: try!(Mmap::new(uring_fd, sqes_len, IORING_OFF_SQES)),
+            sq_params: params.sq_off,
+        };
+        let cq_ring = try!(Mmap::new(uring_fd, cq_len, IORING_OFF_CQ_RING));
+        Ok((ops, data, cq_ring))
+    }
+
+    // IORING_POLL_ADD_MULTI に対応していないカーネルは、フラグ付きの POLL_ADD を EINVAL で完了する.
+    // 読み込める eventfd を監視して、完了の後も監視が続くかどうかで確かめる.
+    fn probe_multishot(&self) -> io::Result<()> {
+        let efd = libc_try!(eventfd(1, EFD_CLOEXEC));
+        let id = {
+            let mut uring = self.mutex.lock().unwrap();
+            let id = uring.next_id();
+            uring.poll_add(id, efd, EPOLLIN as u32);
+            uring.submit(self.uring_fd);
+            id
+        };
+        let mut multishot = None;
+        loop {
+            let cqe = match self.next_cqe() {
+                Some(cqe) => cqe,
+                None => {
+                    self.wait(-1);
+                    continue;
+                },
+            };
+            if cqe.user_data != id {
+                continue;
+            }
+            if (cqe.flags & IORING_CQE_F_MORE) == 0 {
+                break;
+            }
+            if multishot.is_none() {
+                multishot = Some(true);
+                let mut uring = self.mutex.lock().unwrap();
+                uring.poll_remove(id);
+                uring.submit(self.uring_fd);
+            }
+        }
+        libc_ign!(close(efd));
+        match multishot {
+            Some(true) => Ok(()),
+            _ => Err(unsupported("IORING_POLL_ADD_MULTI")),
+        }
+    }
+
+    fn next_cqe(&self) -> Option<io_uring_cqe> {
+        unsafe {
+            let head = self.cq_ring.at::<AtomicU32>(self.cq_params.head);
+            let tail = self.cq_ring.at::<AtomicU32>(self.cq_params.tail);
+            let mask = *self.cq_ring.at::<u32>(self.cq_params.ring_mask);
+            let idx = (*head).load(Ordering::Relaxed);
+            if idx == (*tail).load(Ordering::Acquire) {
+                return None;
+            }
+            let cqe = ptr::read(self.cq_ring.at::<io_uring_cqe>(self.cq_params.cqes).offset((idx & mask) as isize));
+            (*head).store(idx.wrapping_add(1), Ordering::Release);
+            Some(cqe)
+        }
+    }
+
+    fn wait(&self, msec: i32) {
+        let res = if msec < 0 {
+            io_uring_enter(self.uring_fd, 0, 1, IORING_ENTER_GETEVENTS, ptr::null(), 0)
+        } else {
+            let ts = timespec {
+                tv_sec: (msec / 1000) as _,
+                tv_nsec: ((msec % 1000) * 1000000) as _,
+            };
+            let arg = io_uring_getevents_arg {
+                sigmask: 0,
+                sigmask_sz: 0,
+                pad: 0,
+                ts: &ts as *const _ as u64,
+            };
+            io_uring_enter(self.uring_fd, 0, 1, IORING_ENTER_GETEVENTS | IORING_ENTER_EXT_ARG,
+                           &arg as *const _ as *const c_void, mem::size_of::<io_uring_getevents_arg>())
+        };
+        if let Err(err) = res {
+            match err.raw_os_error() {
+                Some(EINTR) | Some(ETIME) | Some(EAGAIN) | Some(EBUSY) => (),
+                _ => panic!("{}", err),
+            }
+        }
+    }
+}
+
+impl Reactor for UringReactor {
+    fn register(&self, fd: RawFd, token: usize, intr: bool) {
+        let mut uring = self.mutex.lock().unwrap();
+        let id = uring.next_id();
+        let events = if intr {
+            EPOLLIN as u32
+        } else {
+            (EPOLLIN | EPOLLOUT | EPOLLET) as u32
+        };
+        uring.polling_entry.insert(id, (token, fd, events));
+        uring.polling_token.insert(token, id);
+        uring.poll_add(id, fd, events);
+        uring.submit(self.uring_fd);
+    }
+
+    fn deregister(&self, _fd: RawFd, token: usize) {
+        let mut uring = self.mutex.lock().unwrap();
+        if let Some(id) = uring.polling_token.remove(&token) {
+            uring.polling_entry.remove(&id);
+            uring.poll_remove(id);
+            uring.submit(self.uring_fd);
+        }
+    }
+
+    fn poll(&self, timeout: Option<i32>, events: &mut Vec<ReactorEvent>) {
+        if let Some(msec) = timeout {
+            self.wait(msec);
+        }
+
+        let mut uring = self.mutex.lock().unwrap();
+        while let Some(cqe) = self.next_cqe() {
+            let (token, fd, mask) = match uring.polling_entry.get(&cqe.user_data) {
+                Some(&entry) => entry,
+                None => {
+                    match uring.pending.remove(&cqe.user_data) {
+                        Some(Pending::Op(token, input, _)) => {
+                            uring.pending_op.remove(&(token, input));
+                            events.push(ReactorEvent {
+                                token: token,
+                                readable: input,
+                                writable: !input,
+                                error: false,
+                                result: Some(cqe.res),
+                            });
+                        },
+                        // タイマーの満了は poll から戻るだけで、満了したハンドラは IoService が取り出す.
+                        Some(Pending::Timeout(_)) => if uring.timeout == Some(cqe.user_data) {
+                            uring.timeout = None;
+                        },
+                        // 解除済みのエントリや、取り消しの完了は無視する.
+                        None => (),
+                    }
+                    continue;
+                },
+            };
+            if cqe.res < 0 {
+                // 監視に失敗した. 再登録すると同じ失敗を繰り返すので、操作を試させてエラーを得させる.
+                uring.polling_entry.remove(&cqe.user_data);
+                uring.polling_token.remove(&token);
+                events.push(ReactorEvent {
+                    token: token,
+                    readable: true,
+                    writable: true,
+                    error: false,
+                    result: None,
+                });
+                continue;
+            }
+            if (cqe.flags & IORING_CQE_F_MORE) == 0 {
+                uring.poll_add(cqe.user_data, fd, mask);
+            }
+            let revents = cqe.res as u32;
+            events.push(ReactorEvent {
+                token: token,
+                readable: (revents & EPOLLIN as u32) != 0,
+                writable: (revents & EPOLLOUT as u32) != 0,
+                error: (revents & (EPOLLERR | EPOLLHUP) as u32) != 0,
+                result: None,
+            });
+        }
+        uring.submit(self.uring_fd);
+    }
+
+    fn performs(&self, op: &ReactorOp) -> bool {
+        (self.ops & (1 << opcode(op))) != 0
+    }
+
+    fn submit(&self, fd: RawFd, token: usize, input: bool, op: ReactorOp) {
+        let mut uring = self.mutex.lock().unwrap();
+        let id = uring.next_id();
+        let mut addr = None;
+        match op {
+            ReactorOp::Read(buf, len) => uring.rw(id, IORING_OP_READ, fd, buf as u64, len),
+            ReactorOp::Write(buf, len) => uring.rw(id, IORING_OP_WRITE, fd, buf as u64, len),
+            ReactorOp::ReadV(iov, len) => uring.rw(id, IORING_OP_READV, fd, iov as u64, len),
+            ReactorOp::WriteV(iov, len) => uring.rw(id, IORING_OP_WRITEV, fd, iov as u64, len),
+            ReactorOp::Accept => uring.push(io_uring_sqe {
+                opcode: IORING_OP_ACCEPT,
+                fd: fd,
+                user_data: id,
+                .. io_uring_sqe::default()
+            }),
+            ReactorOp::Connect(ss, len) => {
+                let ss = Box::new(ss);
+                uring.push(io_uring_sqe {
+                    opcode: IORING_OP_CONNECT,
+                    fd: fd,
+                    addr: &*ss as *const _ as u64,
+                    off: len as u64,
+                    user_data: id,
+                    .. io_uring_sqe::default()
+                });
+                addr = Some(ss);
+            },
+        }
+        uring.pending.insert(id, Pending::Op(token, input, addr));
+        uring.pending_op.insert((token, input), id);
+        uring.submit(self.uring_fd);
+    }
+
+    fn abort(&self, _fd: RawFd, token: usize, input: bool) {
+        let mut uring = self.mutex.lock().unwrap();
+        if let Some(&id) = uring.pending_op.get(&(token, input)) {
+            uring.cancel(IORING_OP_ASYNC_CANCEL, id);
+            uring.submit(self.uring_fd);
+        }
+    }
+
+    fn reset_timeout(&self, expiry: Expiry) -> bool {
+        let mut uring = self.mutex.lock().unwrap();
+        if let Some(id) = uring.timeout.take() {
+            uring.cancel(IORING_OP_TIMEOUT_REMOVE, id);
+        }
+        if expiry != Expiry::default() {
+            let id = uring.next_id();
+            let ts = Box::new(kernel_timespec {
+                tv_sec: expiry.as_secs() as i64,
+                tv_nsec: expiry.subsec_nanos() as i64,
+            });
+            uring.push(io_uring_sqe {
+                opcode: IORING_OP_TIMEOUT,
+                fd: -1,
+                addr: &*ts as *const _ as u64,
+                len: 1,
+                op_flags: IORING_TIMEOUT_ABS,
+                user_data: id,
+                .. io_uring_sqe::default()
+            });
+            uring.pending.insert(id, Pending::Timeout(ts));
+            uring.timeout = Some(id);
+        }
+        uring.submit(self.uring_fd);
+        true
+    }
+}
+
+impl Drop for UringReactor {
+    fn drop(&mut self) {
+        libc_ign!(close(self.uring_fd));
+    }
+}
+
+#[cfg(test)]
+use io_service::IoService;
+#[cfg(test)]
+use std::time::{Duration, Instant};
+#[cfg(test)]
+use clock::IntoExpiry;
+#[cfg(test)]
+use super::reactor::backend_test;
+
+// io_uring が使えない環境ではテストしない.
+#[cfg(test)]
+fn uring_service() -> Option<IoService> {
+    UringReactor::try_new().ok().map(IoService::with_reactor)
+}
+
+#[test]
+fn test_uring_many_fds() {
+    let react = match UringReactor::try_new() {
+        Ok(react) => react,
+        Err(_) => return,
+    };
+
+    // SQ の大きさ (URING_ENTRIES) を超える数を登録する.
+    let mut fds = Vec::new();
+    for i in 0..(URING_ENTRIES as usize + 44) {
+        let mut pipefd = [0; 2];
+        assert_eq!(unsafe { ::libc::pipe(pipefd.as_mut_ptr()) }, 0);
+        react.register(pipefd[1], i + 1, false);
+        fds.extend_from_slice(&pipefd);
+    }
+
+    let mut tokens = Vec::new();
+    for _ in 0..10 {
+        let mut events = Vec::new();
+        react.poll(Some(100), &mut events);
+        tokens.extend(events.iter().filter(|ev| ev.writable).map(|ev| ev.token));
+        tokens.sort();
+        tokens.dedup();
+        if tokens.len() == URING_ENTRIES as usize + 44 {
+            break;
+        }
+    }
+    assert_eq!(tokens, (1..(URING_ENTRIES as usize + 45)).collect::<Vec<_>>());
+
+    for (i, &fd) in fds.iter().enumerate() {
+        if i % 2 == 1 {
+            react.deregister(fd, i / 2 + 1);
+        }
+        unsafe { ::libc::close(fd) };
+    }
+}
+
+#[cfg(test)]
+fn poll_results(react: &UringReactor, len: usize) -> HashMap<usize, i32> {
+    let mut results = HashMap::new();
+    for _ in 0..10 {
+        let mut events = Vec::new();
+        react.poll(Some(100), &mut events);
+        results.extend(events.iter().filter_map(|ev| ev.result.map(|res| (ev.token, res))));
+        if results.len() == len {
+            break;
+        }
+    }
+    results
+}
+
+#[test]
+fn test_uring_submit() {
+    let react = match UringReactor::try_new() {
+        Ok(react) => react,
+        Err(_) => return,
+    };
+
+    let mut pipefd = [0; 2];
+    assert_eq!(unsafe { ::libc::pipe(pipefd.as_mut_ptr()) }, 0);
+    let mut buf = [0; 8];
+    assert!(react.performs(&ReactorOp::Read(buf.as_mut_ptr(), buf.len())));
+
+    // 書き込む前に投入した読み込みが、書き込んだデータで完了する.
+    react.submit(pipefd[0], 1, true, ReactorOp::Read(buf.as_mut_ptr(), buf.len()));
+    react.submit(pipefd[1], 2, false, ReactorOp::Write(b"hello".as_ptr(), 5));
+    let results = poll_results(&react, 2);
+    assert_eq!(results.get(&1), Some(&5));
+    assert_eq!(results.get(&2), Some(&5));
+    assert_eq!(&buf[..5], b"hello");
+
+    // 取り消した読み込みは ECANCELED で完了する.
+    react.submit(pipefd[0], 1, true, ReactorOp::Read(buf.as_mut_ptr(), buf.len()));
+    react.abort(pipefd[0], 1, true);
+    assert_eq!(poll_results(&react, 1).get(&1), Some(&-::libc::ECANCELED));
+
+    unsafe { ::libc::close(pipefd[0]) };
+    unsafe { ::libc::close(pipefd[1]) };
+}
+
+#[test]
+fn test_uring_timeout() {
+    let react = match UringReactor::try_new() {
+        Ok(react) => react,
+        Err(_) => return,
+    };
+
+    // 満了すると poll から戻る.
+    let now = Instant::now();
+    assert!(react.reset_timeout((now + Duration::from_millis(50)).into_expiry()));
+    let mut events = Vec::new();
+    react.poll(Some(10000), &mut events);
+    assert!(events.is_empty());
+    assert!(now.elapsed() >= Duration::from_millis(50));
+    assert!(now.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_uring_timer() {
+    if let Some(io) = uring_service() {
+        backend_test::timer(&io);
+    }
+}
+
+#[test]
+fn test_uring_echo() {
+    if let Some(io) = uring_service() {
+        backend_test::echo(&io);
+    }
+}
+
+#[test]
+fn test_uring_cancel() {
+    if let Some(io) = uring_service() {
+        backend_test::cancel(&io);
+    }
+}
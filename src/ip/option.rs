@@ -4,7 +4,9 @@ use libc::{IPPROTO_IP, IPPROTO_IPV6, IPPROTO_TCP, IPV6_V6ONLY, TCP_NODELAY,
            IP_TTL, IP_MULTICAST_TTL, IP_MULTICAST_LOOP, IPV6_MULTICAST_LOOP,
            IP_ADD_MEMBERSHIP, IP_DROP_MEMBERSHIP,
            c_void, in_addr,  in6_addr, ip_mreq, ipv6_mreq};
-use super::{IpProtocol, IpAddrV4, IpAddrV6, IpAddr, Tcp};
+use super::{IpProtocol, IpAddrV4, IpAddrV6, IpAddr, Tcp, Udp};
+#[cfg(target_os = "linux")]
+use libc::SOL_UDP;
 
 const IPV6_UNICAST_HOPS: i32 = 16;
 const IPV6_MULTICAST_HOPS: i32 = 18;
@@ -12,6 +14,10 @@ const IPV6_JOIN_GROUP: i32 = 20;
 const IPV6_LEAVE_GROUP: i32 = 21;
 const IP_MULTICAST_IF: i32 = 32;
 const IPV6_MULTICAST_IF: i32 = 17;
+#[cfg(target_os = "linux")]
+pub(crate) const UDP_SEGMENT: i32 = 103;
+#[cfg(target_os = "linux")]
+pub(crate) const UDP_GRO: i32 = 104;
 
 fn in_addr_of(addr: IpAddrV4) -> in_addr {
     let ptr = addr.as_bytes() as *const _ as *const in_addr;
@@ -661,6 +667,159 @@ impl<P: IpProtocol> SetSocketOption<P> for OutboundInterface {
     }
 }
 
+/// Socket option for the segment size of the UDP generic segmentation offload (GSO).
+///
+/// Implements the SOL_UDP/UDP_SEGMENT socket option.
+/// If it is set, each buffer sent is split into datagrams of the size by the kernel or the NIC.
+/// `0` disables the segmentation.
+///
+/// # Examples
+/// Setting the option:
+///
+/// ```
+/// use asyncio::*;
+/// use asyncio::ip::*;
+///
+/// let io = &IoService::new();
+/// let soc = UdpSocket::new(io, Udp::v4()).unwrap();
+///
+/// soc.set_option(UdpSegment::new(1200)).unwrap();
+/// ```
+///
+/// Getting the option:
+///
+/// ```
+/// use asyncio::*;
+/// use asyncio::ip::*;
+///
+/// let io = &IoService::new();
+/// let soc = UdpSocket::new(io, Udp::v4()).unwrap();
+///
+/// let opt: UdpSegment = soc.get_option().unwrap();
+/// let size: u16 = opt.get();
+/// ```
+#[cfg(target_os = "linux")]
+#[derive(Default, Clone)]
+pub struct UdpSegment(i32);
+
+#[cfg(target_os = "linux")]
+impl UdpSegment {
+    pub fn new(size: u16) -> UdpSegment {
+        UdpSegment(size as i32)
+    }
+
+    pub fn get(&self) -> u16 {
+        self.0 as u16
+    }
+
+    pub fn set(&mut self, size: u16) {
+        self.0 = size as i32
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SocketOption<Udp> for UdpSegment {
+    type Data = i32;
+
+    fn level(&self, _: &Udp) -> i32 {
+        SOL_UDP
+    }
+
+    fn name(&self, _: &Udp) -> i32 {
+        UDP_SEGMENT
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl GetSocketOption<Udp> for UdpSegment {
+    fn data_mut(&mut self) -> &mut Self::Data {
+        &mut self.0
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SetSocketOption<Udp> for UdpSegment {
+    fn data(&self) -> &Self::Data {
+        &self.0
+    }
+}
+
+/// Socket option to receive the UDP datagrams coalesced by the generic receive offload (GRO).
+///
+/// Implements the SOL_UDP/UDP_GRO socket option.
+/// The segment size of the coalesced datagrams is reported by `UdpSocket::receive_from_segmented`.
+///
+/// # Examples
+/// Setting the option:
+///
+/// ```
+/// use asyncio::*;
+/// use asyncio::ip::*;
+///
+/// let io = &IoService::new();
+/// let soc = UdpSocket::new(io, Udp::v4()).unwrap();
+///
+/// soc.set_option(UdpGro::new(true)).unwrap();
+/// ```
+///
+/// Getting the option:
+///
+/// ```
+/// use asyncio::*;
+/// use asyncio::ip::*;
+///
+/// let io = &IoService::new();
+/// let soc = UdpSocket::new(io, Udp::v4()).unwrap();
+///
+/// let opt: UdpGro = soc.get_option().unwrap();
+/// let is_set: bool = opt.get();
+/// ```
+#[cfg(target_os = "linux")]
+#[derive(Default, Clone)]
+pub struct UdpGro(i32);
+
+#[cfg(target_os = "linux")]
+impl UdpGro {
+    pub fn new(on: bool) -> UdpGro {
+        UdpGro(on as i32)
+    }
+
+    pub fn get(&self) -> bool {
+        self.0 != 0
+    }
+
+    pub fn set(&mut self, on: bool) {
+        self.0 = on as i32
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SocketOption<Udp> for UdpGro {
+    type Data = i32;
+
+    fn level(&self, _: &Udp) -> i32 {
+        SOL_UDP
+    }
+
+    fn name(&self, _: &Udp) -> i32 {
+        UDP_GRO
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl GetSocketOption<Udp> for UdpGro {
+    fn data_mut(&mut self) -> &mut Self::Data {
+        &mut self.0
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SetSocketOption<Udp> for UdpGro {
+    fn data(&self) -> &Self::Data {
+        &self.0
+    }
+}
+
 #[test]
 fn test_outbound_interface() {
     assert_eq!(mem::size_of::<u32>(), mem::size_of::<in_addr>());
@@ -2,6 +2,8 @@ use std::io;
 use std::mem;
 use traits::{Protocol, Endpoint};
 use io_service::{Handler};
+#[cfg(target_os = "linux")]
+use fd_ops::{recvmsg_gro, async_recvmsg_gro, sendmsg_gso, async_sendmsg_gso};
 use dgram_socket::{DgramSocket};
 use libc::{AF_INET, AF_INET6, SOCK_DGRAM};
 use super::{IpProtocol, IpEndpoint, Resolver, ResolverIter, ResolverQuery, Passive};
@@ -181,6 +183,36 @@ pub type UdpEndpoint = IpEndpoint<Udp>;
 /// ```
 pub type UdpSocket = DgramSocket<Udp>;
 
+#[cfg(target_os = "linux")]
+impl DgramSocket<Udp> {
+    /// Asynchronously receives a datagram, and the segment size if it has been coalesced by GRO.
+    ///
+    /// `UdpGro` needs to be set to receive the coalesced datagrams. Then the buffer may hold
+    /// several datagrams of the segment size, the last of which may be shorter.
+    pub fn async_receive_from_segmented<F>(&self, buf: &mut [u8], flags: i32, handler: F) -> F::Output
+        where F: Handler<(usize, UdpEndpoint, Option<usize>)>,
+    {
+        async_recvmsg_gro(self, buf, flags, unsafe { self.protocol().uninitialized() }, handler)
+    }
+
+    /// Asynchronously sends the buffer as datagrams of `segment` bytes with GSO.
+    ///
+    /// The last datagram may be shorter. It overrides the `UdpSegment` option for this call.
+    pub fn async_send_to_segmented<F>(&self, buf: &[u8], flags: i32, ep: UdpEndpoint, segment: u16, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_sendmsg_gso(self, buf, flags, ep, segment, handler)
+    }
+
+    pub fn receive_from_segmented(&self, buf: &mut [u8], flags: i32) -> io::Result<(usize, UdpEndpoint, Option<usize>)> {
+        recvmsg_gro(self, buf, flags, unsafe { self.protocol().uninitialized() })
+    }
+
+    pub fn send_to_segmented(&self, buf: &[u8], flags: i32, ep: UdpEndpoint, segment: u16) -> io::Result<usize> {
+        sendmsg_gso(self, buf, flags, ep, segment)
+    }
+}
+
 /// The UDP resolver type.
 pub type UdpResolver = Resolver<Udp>;
 
@@ -209,3 +241,48 @@ fn test_udp_resolve() {
         assert!(ep.port() == 80);
     }
 }
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_udp_segmented() {
+    use IoService;
+    use super::*;
+
+    let io = &IoService::new();
+    let rx = UdpSocket::new(io, Udp::v4()).unwrap();
+    rx.bind(&UdpEndpoint::new(IpAddrV4::loopback(), 0)).unwrap();
+    let ep = rx.local_endpoint().unwrap();
+    let tx = UdpSocket::new(io, Udp::v4()).unwrap();
+
+    // GRO を有効にしていなければ分割されたまま受信する.
+    let buf: Vec<u8> = (0..2500).map(|i| i as u8).collect();
+    assert_eq!(tx.send_to_segmented(&buf, 0, ep.clone(), 1000).unwrap(), 2500);
+    let mut recv = [0; 4096];
+    for &(off, len) in &[(0, 1000), (1000, 1000), (2000, 500)] {
+        let (n, _, segment) = rx.receive_from_segmented(&mut recv, 0).unwrap();
+        assert_eq!(n, len);
+        assert_eq!(&recv[..n], &buf[off..off + len]);
+        assert_eq!(segment, None);
+    }
+
+    // UDP_GRO に対応していないカーネルでは結合を確認できない.
+    if rx.set_option(UdpGro::new(true)).is_err() {
+        return;
+    }
+    assert!(rx.get_option::<UdpGro>().unwrap().get());
+    tx.set_option(UdpSegment::new(1000)).unwrap();
+    assert_eq!(tx.get_option::<UdpSegment>().unwrap().get(), 1000);
+    assert_eq!(tx.send_to(&buf, 0, ep.clone()).unwrap(), 2500);
+    let mut total = 0;
+    let mut coalesced = false;
+    while total < 2500 {
+        let (n, _, segment) = rx.receive_from_segmented(&mut recv[..], 0).unwrap();
+        assert_eq!(&recv[..n], &buf[total..total + n]);
+        if let Some(segment) = segment {
+            assert_eq!(segment, 1000);
+            coalesced = true;
+        }
+        total += n;
+    }
+    assert!(coalesced);
+}
@@ -138,7 +138,14 @@ pub use self::traits::*;
 pub mod clock;
 
 mod io_service;
-pub use self::io_service::{IoObject, FromRawFd, IoService, IoServiceWork, Handler, Strand, StrandImmutable, wrap};
+pub use self::io_service::{IoObject, FromRawFd, IoService, IoServiceWork, IoServicePool, IoServiceStats, PanicPolicy, Handler, HandlerMemory, Strand, StrandImmutable, wrap};
+pub use self::io_service::{UseFuture, IoFuture, use_future, UsePromise, Promise, use_promise};
+pub use self::io_service::{CancellationSignal, CancellationHandler, bind_cancellation};
+pub use self::io_service::{TimeoutHandler, with_timeout};
+pub use self::io_service::{Reactor, ReactorEvent, ReactorOp, PollReactor, MockReactor};
+#[cfg(all(feature = "epoll", target_os = "linux"))] pub use self::io_service::EpollReactor;
+#[cfg(all(feature = "io_uring", target_os = "linux"))] pub use self::io_service::UringReactor;
+#[cfg(all(feature = "kqueue", target_os = "macos"))] pub use self::io_service::KqueueReactor;
 #[cfg(feature = "context")] pub use self::io_service::Coroutine;
 
 //---------
@@ -155,6 +162,7 @@ pub use self::streambuf::StreamBuf;
 
 mod stream;
 pub use self::stream::{Stream, MatchCondition, read_until, write_until, async_read_until, async_write_until};
+pub use self::stream::{read_exact, write_all, async_read_exact, async_write_all};
 
 mod stream_socket;
 pub use self::stream_socket::StreamSocket;
@@ -0,0 +1,158 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use io_service::Handler;
+use fd_ops::{recvmsg_fds, sendmsg_fds, async_recvmsg_fds, async_sendmsg_fds};
+use stream_socket::StreamSocket;
+use dgram_socket::DgramSocket;
+use seq_packet_socket::SeqPacketSocket;
+use super::{LocalStream, LocalDgram, LocalSeqPacket};
+#[cfg(target_os = "linux")]
+use fd_ops::{recvmsg_creds, async_recvmsg_creds};
+#[cfg(target_os = "linux")]
+use traits::Protocol;
+#[cfg(target_os = "linux")]
+use super::{LocalDgramEndpoint, PeerCred};
+
+macro_rules! impl_fd_passing {
+    ($socket:ident, $protocol:ident) => {
+        impl $socket<$protocol> {
+            /// Receives the data and the file descriptors passed by `SCM_RIGHTS`.
+            ///
+            /// The received file descriptors are owned by the caller, and are closed if an error occurs.
+            /// If the file descriptors were truncated, they are all closed and the flag is `true`;
+            /// the received data is returned either way.
+            ///
+            /// The close-on-exec flag is set on the received file descriptors. Except on Linux,
+            /// it is set after receiving, so a child forked by another thread in between may inherit them.
+            pub fn recv_fds(&self, buf: &mut [u8], flags: i32) -> io::Result<(usize, Vec<RawFd>, bool)> {
+                recvmsg_fds(self, buf, flags)
+            }
+
+            /// Asynchronously receives the data and the file descriptors passed by `SCM_RIGHTS`.
+            pub fn async_recv_fds<F>(&self, buf: &mut [u8], flags: i32, handler: F) -> F::Output
+                where F: Handler<(usize, Vec<RawFd>, bool)>,
+            {
+                async_recvmsg_fds(self, buf, flags, handler)
+            }
+
+            /// Sends the data with the file descriptors by `SCM_RIGHTS`.
+            pub fn send_fds(&self, buf: &[u8], fds: &[RawFd], flags: i32) -> io::Result<usize> {
+                sendmsg_fds(self, buf, fds, flags)
+            }
+
+            /// Asynchronously sends the data with the file descriptors by `SCM_RIGHTS`.
+            pub fn async_send_fds<F>(&self, buf: &[u8], fds: &[RawFd], flags: i32, handler: F) -> F::Output
+                where F: Handler<usize>,
+            {
+                async_sendmsg_fds(self, buf, fds, flags, handler)
+            }
+        }
+    }
+}
+
+impl_fd_passing!(StreamSocket, LocalStream);
+impl_fd_passing!(DgramSocket, LocalDgram);
+impl_fd_passing!(SeqPacketSocket, LocalSeqPacket);
+
+#[cfg(target_os = "linux")]
+impl DgramSocket<LocalDgram> {
+    /// Receives a datagram with the credentials of the sender passed by `SCM_CREDENTIALS`.
+    ///
+    /// The credentials are passed if the `PassCred` option is set, otherwise `None`.
+    pub fn recv_creds(&self, buf: &mut [u8], flags: i32) -> io::Result<(usize, LocalDgramEndpoint, Option<PeerCred>)> {
+        recvmsg_creds(self, buf, flags, unsafe { LocalDgram.uninitialized() })
+    }
+
+    /// Asynchronously receives a datagram with the credentials of the sender passed by `SCM_CREDENTIALS`.
+    pub fn async_recv_creds<F>(&self, buf: &mut [u8], flags: i32, handler: F) -> F::Output
+        where F: Handler<(usize, LocalDgramEndpoint, Option<PeerCred>)>,
+    {
+        async_recvmsg_creds(self, buf, flags, unsafe { LocalDgram.uninitialized() }, handler)
+    }
+}
+
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+#[cfg(test)]
+use std::os::unix::io::AsRawFd;
+#[cfg(test)]
+use io_service::{IoService, wrap};
+#[cfg(test)]
+use super::{LocalStreamSocket, LocalDgramSocket, connect_pair};
+#[cfg(test)]
+use libc;
+
+#[test]
+fn test_send_recv_fds() {
+    let io = &IoService::new();
+    let (tx, rx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let (a, b): (LocalDgramSocket, LocalDgramSocket) = connect_pair(io, LocalDgram).unwrap();
+
+    assert_eq!(tx.send_fds(b"fd", &[a.as_raw_fd()], 0).unwrap(), 2);
+    let mut buf = [0; 8];
+    let (len, fds, truncated) = rx.recv_fds(&mut buf, 0).unwrap();
+    assert_eq!(&buf[..len], b"fd");
+    assert!(!truncated);
+    assert_eq!(fds.len(), 1);
+    assert!(fds[0] != a.as_raw_fd());
+    assert!(unsafe { libc::fcntl(fds[0], libc::F_GETFD) } & libc::FD_CLOEXEC != 0);
+
+    // 受け取った記述子は同じソケットを指している.
+    assert_eq!(unsafe { libc::send(fds[0], b"hi".as_ptr() as *const _, 2, 0) }, 2);
+    assert_eq!(b.receive(&mut buf, 0).unwrap(), 2);
+    unsafe { libc::close(fds[0]) };
+}
+
+#[test]
+fn test_async_recv_fds() {
+    let io = &IoService::new();
+    let (tx, rx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let rx = Arc::new(rx);
+    let res = Arc::new(Mutex::new(None));
+
+    let mut buf = [0; 8];
+    let res_ = res.clone();
+    rx.async_recv_fds(&mut buf, 0, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<(usize, Vec<RawFd>, bool)>| {
+        *res_.lock().unwrap() = Some(r);
+    }, &rx));
+    tx.send_fds(b"x", &[0, 1], 0).unwrap();
+    io.run();
+
+    let (len, fds, truncated) = res.lock().unwrap().take().unwrap().unwrap();
+    assert_eq!(len, 1);
+    assert!(!truncated);
+    assert_eq!(fds.len(), 2);
+    for fd in fds {
+        unsafe { libc::close(fd) };
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_async_recv_creds() {
+    use super::PassCred;
+
+    let io = &IoService::new();
+    let (tx, rx): (LocalDgramSocket, LocalDgramSocket) = connect_pair(io, LocalDgram).unwrap();
+    let rx = Arc::new(rx);
+    let res = Arc::new(Mutex::new(None));
+
+    let mut buf = [0; 8];
+    assert!(rx.recv_creds(&mut buf, libc::MSG_DONTWAIT).is_err());
+    tx.send(b"nocred", 0).unwrap();
+    assert!(rx.recv_creds(&mut buf, 0).unwrap().2.is_none());
+
+    rx.set_option(PassCred::new(true)).unwrap();
+    let res_ = res.clone();
+    rx.async_recv_creds(&mut buf, 0, wrap(move |_: Arc<LocalDgramSocket>, r: io::Result<(usize, LocalDgramEndpoint, Option<PeerCred>)>| {
+        *res_.lock().unwrap() = Some(r);
+    }, &rx));
+    tx.send(b"cred", 0).unwrap();
+    io.run();
+
+    let (len, _, cred) = res.lock().unwrap().take().unwrap().unwrap();
+    assert_eq!(len, 4);
+    let cred = cred.unwrap();
+    assert_eq!(cred.pid(), unsafe { libc::getpid() });
+    assert_eq!(cred.uid(), unsafe { libc::getuid() });
+}
@@ -1,5 +1,8 @@
+use std::io;
 use std::mem;
 use {Protocol, Endpoint, DgramSocket};
+#[cfg(target_os = "linux")]
+use fd_ops::autobind;
 use libc::{AF_UNIX, SOCK_DGRAM};
 use super::{LocalProtocol, LocalEndpoint};
 
@@ -59,6 +62,17 @@ pub type LocalDgramEndpoint = LocalEndpoint<LocalDgram>;
 /// The datagram-oriented UNIX domain socket type.
 pub type LocalDgramSocket = DgramSocket<LocalDgram>;
 
+#[cfg(target_os = "linux")]
+impl DgramSocket<LocalDgram> {
+    /// Binds to an unique address in the abstract namespace chosen by the kernel, and returns it.
+    ///
+    /// The peer can reply to the socket that is not bound to any path.
+    pub fn autobind(&self) -> io::Result<LocalDgramEndpoint> {
+        try!(autobind(self));
+        self.local_endpoint()
+    }
+}
+
 #[test]
 fn test_dgram() {
     assert!(LocalDgram == LocalDgram);
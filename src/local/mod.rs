@@ -4,7 +4,9 @@ use std::cmp;
 use std::mem;
 use std::hash;
 use std::slice;
-use std::ffi::{CStr, CString};
+use std::path::Path;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
 use std::marker::PhantomData;
 use error::{invalid_argument};
 use traits::{Protocol, SockAddr};
@@ -33,34 +35,131 @@ impl<P: Protocol> LocalEndpoint<P> {
     pub fn new<T>(path_name: T) -> io::Result<LocalEndpoint<P>>
         where T: AsRef<str>
     {
-        match CString::new(path_name.as_ref()) {
-            Ok(ref s) if s.as_bytes().len() < (mem::size_of::<sockaddr_un>() - 2) => {
-                let src = s.as_bytes_with_nul();
-                let mut ep = LocalEndpoint {
-                    sun: SockAddrImpl::new(AF_UNIX, src.len() + 2),
-                    _marker: PhantomData,
-                };
-                let dst = unsafe { slice::from_raw_parts_mut(ep.sun.sun_path.as_mut_ptr() as *mut u8, src.len()) };
-                dst.clone_from_slice(src);
-                Ok(ep)
+        Self::from_path(path_name.as_ref())
+    }
+
+    /// Returns a `LocalEndpoint` of the file system path, which may not be valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use asyncio::local::LocalStreamEndpoint;
+    ///
+    /// let ep = LocalStreamEndpoint::from_path(Path::new("/tmp/foo.sock")).unwrap();
+    /// assert_eq!(ep.as_path(), Some(Path::new("/tmp/foo.sock")));
+    /// ```
+    pub fn from_path<T>(path: T) -> io::Result<LocalEndpoint<P>>
+        where T: AsRef<OsStr>
+    {
+        let src = path.as_ref().as_bytes();
+        if src.contains(&0) {
+            return Err(invalid_argument());
+        }
+        // 終端の NUL を含める.
+        Self::from_bytes(src, 1)
+    }
+
+    /// Returns a `LocalEndpoint` of the Linux abstract namespace.
+    ///
+    /// The abstract socket address is not bound to the file system, and may contain any bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use asyncio::local::LocalStreamEndpoint;
+    ///
+    /// let ep = LocalStreamEndpoint::abstract_name(b"foo").unwrap();
+    /// assert!(ep.is_abstract());
+    /// assert_eq!(ep.as_abstract_name(), Some(&b"foo"[..]));
+    /// assert_eq!(ep.to_string(), "@foo");
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn abstract_name(name: &[u8]) -> io::Result<LocalEndpoint<P>> {
+        let mut ep = try!(Self::from_bytes(name, 1));
+        // 先頭の NUL の後に名前が続き、終端の NUL は持たない.
+        let path = ep.path_mut();
+        for i in (0..name.len()).rev() {
+            path[i + 1] = path[i];
+        }
+        path[0] = 0;
+        Ok(ep)
+    }
+
+    fn from_bytes(src: &[u8], extra: usize) -> io::Result<LocalEndpoint<P>> {
+        if src.len() + extra > mem::size_of::<sockaddr_un>() - 2 {
+            return Err(invalid_argument());
+        }
+        let mut ep = LocalEndpoint {
+            sun: SockAddrImpl::new(AF_UNIX, src.len() + extra + 2),
+            _marker: PhantomData,
+        };
+        {
+            let dst = ep.path_mut();
+            dst[..src.len()].clone_from_slice(src);
+            for x in &mut dst[src.len()..] {
+                *x = 0;
             }
-            _ => Err(invalid_argument()),
         }
+        Ok(ep)
+    }
+
+    fn path_mut(&mut self) -> &mut [u8] {
+        let len = self.sun.size() - 2;
+        unsafe { slice::from_raw_parts_mut(self.sun.sun_path.as_mut_ptr() as *mut u8, len) }
+    }
+
+    fn path_bytes(&self) -> &[u8] {
+        let len = self.sun.size().saturating_sub(2);
+        unsafe { slice::from_raw_parts(self.sun.sun_path.as_ptr() as *const u8, len) }
+    }
+
+    /// Returns true if the endpoint is in the Linux abstract namespace.
+    pub fn is_abstract(&self) -> bool {
+        self.path_bytes().first() == Some(&0)
+    }
+
+    /// Returns the abstract name without the leading NUL byte, or `None` if the endpoint is not abstract.
+    pub fn as_abstract_name(&self) -> Option<&[u8]> {
+        if self.is_abstract() {
+            Some(&self.path_bytes()[1..])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the file system path, or `None` if the endpoint is abstract.
+    pub fn as_path(&self) -> Option<&Path> {
+        if self.is_abstract() {
+            return None;
+        }
+        let buf = self.path_bytes();
+        let len = buf.iter().position(|&x| x == 0).unwrap_or(buf.len());
+        Some(Path::new(OsStr::from_bytes(&buf[..len])))
     }
 
     /// Returns a path_name associated with the endpoint.
     ///
+    /// This is lossy: an abstract endpoint or a path that is not valid UTF-8 returns an empty string,
+    /// which cannot be told apart from an unnamed endpoint.
+    /// Use `as_path` or `as_abstract_name` instead.
+    ///
     /// # Example
     ///
     /// ```
+    /// # #![allow(deprecated)]
     /// use asyncio::local::LocalStreamEndpoint;
     ///
     /// let ep = LocalStreamEndpoint::new("foo.sock").unwrap();
     /// assert_eq!(ep.path(), "foo.sock");
+    ///
+    /// let ep = LocalStreamEndpoint::abstract_name(b"foo").unwrap();
+    /// assert_eq!(ep.path(), "");
+    /// assert_eq!(ep.as_abstract_name(), Some(&b"foo"[..]));
     /// ```
+    #[deprecated(since = "0.5.4", note = "lossy for abstract and non-UTF-8 endpoints; use `as_path` or `as_abstract_name`")]
     pub fn path(&self) -> &str {
-        let cstr = unsafe { CStr::from_ptr(self.sun.sun_path.as_ptr()) };
-        cstr.to_str().unwrap()
+        self.as_path().and_then(|path| path.to_str()).unwrap_or("")
     }
 }
 
@@ -116,7 +215,10 @@ impl<P: Protocol> hash::Hash for LocalEndpoint<P> {
 
 impl<P: Protocol> fmt::Display for LocalEndpoint<P> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.path())
+        match self.as_abstract_name() {
+            Some(name) => write!(f, "@{}", String::from_utf8_lossy(name)),
+            None => write!(f, "{}", self.as_path().unwrap().display()),
+        }
     }
 }
 
@@ -142,6 +244,13 @@ pub use self::seq_packet::*;
 mod connect_pair;
 pub use self::connect_pair::*;
 
+pub use socket_listener::LocalBindOptions;
+
+mod ancillary;
+
+#[cfg(target_os = "linux")] mod option;
+#[cfg(target_os = "linux")] pub use self::option::*;
+
 
 #[test]
 fn test_local_endpoint_limit() {
@@ -151,3 +260,48 @@ fn test_local_endpoint_limit() {
     assert!(LocalSeqPacketEndpoint::new(&s[..103]).is_ok());
     assert!(LocalSeqPacketEndpoint::new(&s[..108]).is_err());
 }
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_local_endpoint_abstract() {
+    use std::collections::HashSet;
+
+    let ep1 = LocalStreamEndpoint::abstract_name(b"foo").unwrap();
+    let ep2 = LocalStreamEndpoint::new("foo").unwrap();
+    assert!(ep1.is_abstract());
+    assert!(!ep2.is_abstract());
+    assert!(ep1 != ep2);
+    assert_eq!(ep1, LocalStreamEndpoint::abstract_name(b"foo").unwrap());
+    assert!(ep1 != LocalStreamEndpoint::abstract_name(b"foo\0").unwrap());
+    assert_eq!(ep1.as_path(), None);
+    assert_eq!(ep2.as_abstract_name(), None);
+    assert_eq!(format!("{}", ep2), "foo");
+
+    let set: HashSet<_> = vec![ep1.clone(), ep2.clone(), ep1.clone()].into_iter().collect();
+    assert_eq!(set.len(), 2);
+
+    assert!(LocalStreamEndpoint::abstract_name(&[0xff; 107]).is_ok());
+    assert!(LocalStreamEndpoint::abstract_name(&[0xff; 108]).is_err());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_local_endpoint_non_utf8() {
+    let ep = LocalDgramEndpoint::from_path(OsStr::from_bytes(b"foo\xff.sock")).unwrap();
+    assert_eq!(ep.as_path().unwrap().as_os_str().as_bytes(), b"foo\xff.sock");
+    assert_eq!(ep.path(), "");
+    assert!(LocalDgramEndpoint::from_path(OsStr::from_bytes(b"foo\0bar")).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_local_endpoint_bind_abstract() {
+    use io_service::IoService;
+
+    let io = &IoService::new();
+    let name = format!("asyncio-test-{}", unsafe { ::libc::getpid() });
+    let ep = LocalDgramEndpoint::abstract_name(name.as_bytes()).unwrap();
+    let soc = LocalDgramSocket::new(io, LocalDgram).unwrap();
+    soc.bind(&ep).unwrap();
+    assert_eq!(soc.local_endpoint().unwrap(), ep);
+}
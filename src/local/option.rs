@@ -0,0 +1,155 @@
+use std::mem;
+use libc::{SOL_SOCKET, SO_PEERCRED, SO_PASSCRED, ucred};
+use traits::{SocketOption, GetSocketOption, SetSocketOption};
+use super::LocalProtocol;
+
+/// Socket option for the credentials of the connected peer.
+///
+/// Implements the SOL_SOCKET/SO_PEERCRED socket option.
+/// The credentials are also used for the `SCM_CREDENTIALS` ancillary data.
+///
+/// # Examples
+/// Getting the option:
+///
+/// ```
+/// use asyncio::*;
+/// use asyncio::local::*;
+///
+/// let io = &IoService::new();
+/// let (soc, _): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+///
+/// let cred: PeerCred = soc.get_option().unwrap();
+/// let pid: i32 = cred.pid();
+/// let uid: u32 = cred.uid();
+/// let gid: u32 = cred.gid();
+/// ```
+#[derive(Clone)]
+pub struct PeerCred(ucred);
+
+impl PeerCred {
+    pub fn pid(&self) -> i32 {
+        self.0.pid
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.0.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.0.gid
+    }
+}
+
+impl Default for PeerCred {
+    fn default() -> PeerCred {
+        PeerCred(unsafe { mem::zeroed() })
+    }
+}
+
+impl From<ucred> for PeerCred {
+    fn from(cred: ucred) -> PeerCred {
+        PeerCred(cred)
+    }
+}
+
+impl<P: LocalProtocol> SocketOption<P> for PeerCred {
+    type Data = ucred;
+
+    fn level(&self, _: &P) -> i32 {
+        SOL_SOCKET
+    }
+
+    fn name(&self, _: &P) -> i32 {
+        SO_PEERCRED
+    }
+}
+
+impl<P: LocalProtocol> GetSocketOption<P> for PeerCred {
+    fn data_mut(&mut self) -> &mut Self::Data {
+        &mut self.0
+    }
+}
+
+/// Socket option to receive the credentials of the sender.
+///
+/// Implements the SOL_SOCKET/SO_PASSCRED socket option.
+///
+/// # Examples
+/// Setting the option:
+///
+/// ```
+/// use asyncio::*;
+/// use asyncio::local::*;
+///
+/// let io = &IoService::new();
+/// let soc = LocalDgramSocket::new(io, LocalDgram).unwrap();
+///
+/// soc.set_option(PassCred::new(true)).unwrap();
+/// ```
+///
+/// Getting the option:
+///
+/// ```
+/// use asyncio::*;
+/// use asyncio::local::*;
+///
+/// let io = &IoService::new();
+/// let soc = LocalDgramSocket::new(io, LocalDgram).unwrap();
+///
+/// let opt: PassCred = soc.get_option().unwrap();
+/// let is_set: bool = opt.get();
+/// ```
+#[derive(Default, Clone)]
+pub struct PassCred(i32);
+
+impl<P: LocalProtocol> SocketOption<P> for PassCred {
+    type Data = i32;
+
+    fn level(&self, _: &P) -> i32 {
+        SOL_SOCKET
+    }
+
+    fn name(&self, _: &P) -> i32 {
+        SO_PASSCRED
+    }
+}
+
+impl<P: LocalProtocol> GetSocketOption<P> for PassCred {
+    fn data_mut(&mut self) -> &mut Self::Data {
+        &mut self.0
+    }
+}
+
+impl<P: LocalProtocol> SetSocketOption<P> for PassCred {
+    fn data(&self) -> &Self::Data {
+        &self.0
+    }
+}
+
+impl PassCred {
+    pub fn new(on: bool) -> PassCred {
+        PassCred(on as i32)
+    }
+
+    pub fn get(&self) -> bool {
+        self.0 != 0
+    }
+
+    pub fn set(&mut self, on: bool) {
+        self.0 = on as i32
+    }
+}
+
+#[test]
+fn test_peer_cred() {
+    use libc;
+    use io_service::IoService;
+    use super::{LocalStream, LocalStreamSocket, connect_pair};
+
+    let io = &IoService::new();
+    let (soc, _): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let cred: PeerCred = soc.get_option().unwrap();
+    assert_eq!(cred.pid(), unsafe { libc::getpid() });
+    assert_eq!(cred.uid(), unsafe { libc::getuid() });
+    assert_eq!(cred.gid(), unsafe { libc::getgid() });
+}
@@ -79,8 +79,8 @@ fn test_getsockname_local() {
     let io = IoService::new();
     let soc = LocalStreamSocket::new(&io, LocalStream).unwrap();
     let ep = LocalStreamEndpoint::new("/tmp/asio_foo.sock").unwrap();
-    let _ = fs::remove_file(ep.path());
+    let _ = fs::remove_file(ep.as_path().unwrap());
     soc.bind(&ep).unwrap();
     assert_eq!(soc.local_endpoint().unwrap(), ep);
-    let _ = fs::remove_file(ep.path());
+    let _ = fs::remove_file(ep.as_path().unwrap());
 }
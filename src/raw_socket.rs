@@ -1,4 +1,4 @@
-use std::io;
+use std::io::{self, IoSliceMut};
 use io_service::{IoObject, FromRawFd, IoService, IoActor, Handler, AsyncResult};
 use traits::{Protocol, IoControl, GetSocketOption, SetSocketOption, Shutdown};
 use fd_ops::*;
@@ -126,6 +126,36 @@ impl<P: Protocol> RawSocket<P> {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl<P: Protocol> RawSocket<P> {
+    /// Asynchronously receives up to `bufs.len()` datagrams in one system call.
+    ///
+    /// Each datagram is received into its own buffer. The handler receives the number of datagrams,
+    /// and `msgs` is filled with the length and the sender of each of them in the same order.
+    pub fn async_receive_many<F>(&self, bufs: &mut [IoSliceMut], msgs: &mut Vec<(usize, P::Endpoint)>, flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_recvmmsg(self, bufs, msgs, flags, unsafe { self.pro.uninitialized() }, handler)
+    }
+
+    /// Asynchronously sends each message as a datagram to its endpoint in one system call.
+    ///
+    /// The handler receives the number of datagrams sent, which may be less than `msgs.len()`.
+    pub fn async_send_many<F>(&self, msgs: &[(&[u8], P::Endpoint)], flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_sendmmsg(self, msgs, flags, handler)
+    }
+
+    pub fn receive_many(&self, bufs: &mut [IoSliceMut], msgs: &mut Vec<(usize, P::Endpoint)>, flags: i32) -> io::Result<usize> {
+        recvmmsg(self, bufs, msgs, flags, unsafe { self.pro.uninitialized() })
+    }
+
+    pub fn send_many(&self, msgs: &[(&[u8], P::Endpoint)], flags: i32) -> io::Result<usize> {
+        sendmmsg(self, msgs, flags)
+    }
+}
+
 unsafe impl<P: Protocol> IoObject for RawSocket<P> {
     fn io_service(&self) -> &IoService {
         self.act.io_service()
@@ -1,12 +1,13 @@
 use std::io;
 use std::mem;
 use std::ptr;
+use std::time::Duration;
 use std::os::unix::io::{RawFd, AsRawFd};
 use libc::{self, SFD_CLOEXEC, SIG_SETMASK, c_void, sigset_t, signalfd_siginfo,
            signalfd, sigemptyset, sigaddset, sigdelset, pthread_sigmask};
 use unsafe_cell::{UnsafeRefCell};
 use error::{ErrCode, READY, EINTR, EAGAIN, last_error, eof, stopped};
-use io_service::{IoObject, IoService, Handler, AsyncResult, IoActor};
+use io_service::{IoObject, IoService, Callback, Handler, AsyncResult, NoAsyncResult, IoActor};
 use fd_ops::{AsIoActor, getnonblock, setnonblock, cancel};
 
 /// A list specifying POSIX categories of signal.
@@ -204,6 +205,34 @@ fn signalfd_async_read<T, F>(fd: &T, handler: F, ec: ErrCode)
     }), ec)
 }
 
+struct ShutdownHandler {
+    timeout: Duration,
+}
+
+impl Handler<Signal> for ShutdownHandler {
+    type Output = ();
+
+    fn callback(self, io: &IoService, res: io::Result<Signal>) {
+        if res.is_ok() {
+            io.graceful_stop(self.timeout);
+        }
+    }
+
+    fn wrap<G>(self, callback: G) -> Callback
+        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static
+    {
+        Callback::new(move |io: *const IoService, ec| {
+            callback(unsafe { &*io }, ec, self)
+        })
+    }
+
+    type AsyncResult = NoAsyncResult;
+
+    fn async_result(&self) -> Self::AsyncResult {
+        NoAsyncResult
+    }
+}
+
 /// Provides a signal handing.
 pub struct SignalSet {
     act: IoActor,
@@ -231,6 +260,27 @@ impl SignalSet {
         out.get(self.io_service())
     }
 
+    /// Starts a graceful stop of the `IoService` when any of the signals is delivered.
+    ///
+    /// See `IoService::graceful_stop`.
+    ///
+    /// # Examples
+    /// ```
+    /// use asyncio::{IoService, SignalSet, Signal, raise};
+    /// use std::time::Duration;
+    ///
+    /// let io = &IoService::new();
+    /// let mut sig = SignalSet::new(io).unwrap();
+    /// sig.add(Signal::SIGTERM).unwrap();
+    /// sig.async_shutdown(Duration::from_secs(5));
+    /// raise(Signal::SIGTERM).unwrap();
+    /// io.run();
+    /// assert_eq!(io.stopped(), true);
+    /// ```
+    pub fn async_shutdown(&self, timeout: Duration) {
+        self.async_wait(ShutdownHandler { timeout: timeout })
+    }
+
     pub fn cancel(&self) {
         cancel(self)
     }
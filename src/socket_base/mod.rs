@@ -461,6 +461,10 @@ impl<P: Protocol> GetSocketOption<P> for Linger {
     fn data_mut(&mut self) -> &mut Self::Data {
         unsafe { mem::transmute(&mut self.0) }
     }
+
+    fn capacity(&self) -> usize {
+        mem::size_of_val(&self.0)
+    }
 }
 
 impl<P: Protocol> SetSocketOption<P> for Linger {
@@ -836,3 +840,25 @@ impl<P: Protocol> SetSocketOption<P> for SendLowWatermark {
 
 #[cfg(target_os = "linux")] mod ifreq;
 #[cfg(target_os = "linux")] pub use self::ifreq::*;
+
+#[test]
+fn test_get_option() {
+    use io_service::IoService;
+    use ip::{Tcp, TcpSocket};
+
+    // getsockopt にバッファの長さを渡さないとカーネルは値を書き込まない.
+    let io = &IoService::new();
+    let soc = TcpSocket::new(io, Tcp::v4()).unwrap();
+
+    soc.set_option(ReuseAddr::new(true)).unwrap();
+    assert!(soc.get_option::<ReuseAddr>().unwrap().get());
+
+    soc.set_option(KeepAlive::new(true)).unwrap();
+    assert!(soc.get_option::<KeepAlive>().unwrap().get());
+
+    soc.set_option(Linger::new(Some(30))).unwrap();
+    assert_eq!(soc.get_option::<Linger>().unwrap().get(), Some(30));
+
+    soc.set_option(RecvBufferSize::new(65536)).unwrap();
+    assert!(soc.get_option::<RecvBufferSize>().unwrap().get() >= 65536);
+}
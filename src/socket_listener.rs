@@ -1,14 +1,24 @@
 use std::io;
+use std::fs;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{Ordering, AtomicPtr};
 use std::marker::PhantomData;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::os::unix::ffi::OsStrExt;
+use libc;
 use error::ErrCode;
 use io_service::{IoObject, FromRawFd, IoService, IoActor, Callback, Handler};
-use traits::{Protocol, IoControl, GetSocketOption, SetSocketOption};
+use traits::{Protocol, SockAddr, IoControl, GetSocketOption, SetSocketOption};
+use local::{LocalProtocol, LocalEndpoint};
 use fd_ops::*;
 
 const SOMAXCONN: u32 = 126;
 
 struct AcceptHandler<P, F, S> {
     pro: P,
+    io: Option<IoService>,
     handler: F,
     _marker: PhantomData<S>,
 }
@@ -21,9 +31,12 @@ impl<P, F, S> Handler<(RawFd, P::Endpoint)> for AcceptHandler<P, F, S>
     type Output = F::Output;
 
     fn callback(self, io: &IoService, res: io::Result<(RawFd, P::Endpoint)>) {
-        let AcceptHandler { pro, handler, _marker } = self;
+        let AcceptHandler { pro, io: acc_io, handler, _marker } = self;
         match res {
-            Ok((fd, ep)) => handler.callback(io, Ok((unsafe { S::from_raw_fd(io, pro, fd) }, ep))),
+            Ok((fd, ep)) => {
+                let soc = unsafe { S::from_raw_fd(acc_io.as_ref().unwrap_or(io), pro, fd) };
+                handler.callback(io, Ok((soc, ep)))
+            },
             Err(err)     => handler.callback(io, Err(err))
         };
     }
@@ -31,10 +44,11 @@ impl<P, F, S> Handler<(RawFd, P::Endpoint)> for AcceptHandler<P, F, S>
     fn wrap<G>(self, callback: G) -> Callback
         where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static,
     {
-        let AcceptHandler { pro, handler, _marker } = self;
+        let AcceptHandler { pro, io: acc_io, handler, _marker } = self;
         handler.wrap(move |io, ec, handler| {
             callback(io, ec, AcceptHandler {
                 pro: pro,
+                io: acc_io,
                 handler: handler,
                 _marker: _marker,
             })
@@ -48,10 +62,29 @@ impl<P, F, S> Handler<(RawFd, P::Endpoint)> for AcceptHandler<P, F, S>
     }
 }
 
+// Drop で削除するソケットファイル. 他のプロセスが置き換えていれば削除しない.
+struct SocketPath {
+    path: PathBuf,
+    dev: u64,
+    ino: u64,
+}
+
+impl Drop for SocketPath {
+    fn drop(&mut self) {
+        if let Ok(md) = fs::symlink_metadata(&self.path) {
+            if md.dev() == self.dev && md.ino() == self.ino {
+                let _ = fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
 /// Provides an ability to accept new connections.
 pub struct SocketListener<P: Protocol, S: FromRawFd<P>> {
     pro: P,
     act: IoActor,
+    // bind_with で unlink_on_drop を指定したときだけ一度設定する.
+    path: AtomicPtr<SocketPath>,
     _marker: PhantomData<S>,
 }
 
@@ -72,6 +105,27 @@ impl<P: Protocol, S: FromRawFd<P>> SocketListener<P, S> {
     {
         let handler = AcceptHandler {
             pro: self.protocol(),
+            io: None,
+            handler: handler,
+            _marker: PhantomData,
+        };
+        async_accept(self, unsafe { self.pro.uninitialized() }, handler)
+    }
+
+    /// Accepts a new connection, that is associated with the given `IoService`.
+    pub fn accept_on(&self, io: &IoService) -> io::Result<(S, P::Endpoint)>
+    {
+        let (fd, ep) = try!(accept(self, unsafe { self.pro.uninitialized() }));
+        Ok((unsafe { S::from_raw_fd(io, self.protocol(), fd) }, ep))
+    }
+
+    /// Starts an asynchronous accept, that the new connection is associated with the given `IoService`.
+    pub fn async_accept_on<F>(&self, io: &IoService, handler: F) -> F::Output
+        where F: Handler<(S, P::Endpoint)>,
+    {
+        let handler = AcceptHandler {
+            pro: self.protocol(),
+            io: Some(io.clone()),
             handler: handler,
             _marker: PhantomData,
         };
@@ -135,12 +189,22 @@ impl<P: Protocol, S: FromRawFd<P>> FromRawFd<P> for SocketListener<P, S> {
     unsafe fn from_raw_fd(io: &IoService, pro: P, fd: RawFd) -> SocketListener<P, S> {
         SocketListener {
             pro: pro,
-            act: IoActor::new(io, fd),
+            act: IoActor::new_acceptor(io, fd),
+            path: AtomicPtr::new(ptr::null_mut()),
             _marker: PhantomData,
         }
     }
 }
 
+impl<P: Protocol, S: FromRawFd<P>> Drop for SocketListener<P, S> {
+    fn drop(&mut self) {
+        let path = self.path.load(Ordering::Acquire);
+        if !path.is_null() {
+            drop(unsafe { Box::from_raw(path) });
+        }
+    }
+}
+
 impl<P: Protocol, S: FromRawFd<P>> AsRawFd for SocketListener<P, S> {
     fn as_raw_fd(&self) -> RawFd {
         self.act.as_raw_fd()
@@ -152,3 +216,273 @@ impl<P: Protocol, S: FromRawFd<P>> AsIoActor for SocketListener<P, S> {
         &self.act
     }
 }
+
+/// The options of binding the UNIX domain listener.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use std::os::unix::fs::PermissionsExt;
+/// use asyncio::IoService;
+/// use asyncio::local::{LocalStream, LocalStreamEndpoint, LocalStreamListener, LocalBindOptions};
+///
+/// let io = &IoService::new();
+/// let ep = LocalStreamEndpoint::new("/tmp/asyncio_bind_with.sock").unwrap();
+/// let opts = LocalBindOptions::new().unlink_stale(true).unlink_on_drop(true).mode(0o600);
+///
+/// let sv = LocalStreamListener::new(io, LocalStream).unwrap();
+/// sv.bind_with(&ep, &opts).unwrap();
+/// sv.listen().unwrap();
+/// assert_eq!(fs::metadata("/tmp/asyncio_bind_with.sock").unwrap().permissions().mode() & 0o777, 0o600);
+///
+/// drop(sv);
+/// assert!(fs::metadata("/tmp/asyncio_bind_with.sock").is_err());
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct LocalBindOptions {
+    unlink_stale: bool,
+    unlink_on_drop: bool,
+    mode: Option<u32>,
+    owner: Option<(Option<u32>, Option<u32>)>,
+}
+
+impl LocalBindOptions {
+    pub fn new() -> LocalBindOptions {
+        LocalBindOptions::default()
+    }
+
+    /// Removes the socket file left behind if no one is listening on it.
+    pub fn unlink_stale(mut self, on: bool) -> Self {
+        self.unlink_stale = on;
+        self
+    }
+
+    /// Removes the socket file when the listener is dropped.
+    pub fn unlink_on_drop(mut self, on: bool) -> Self {
+        self.unlink_on_drop = on;
+        self
+    }
+
+    /// Sets the permission bits of the socket file after binding.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Sets the owner and the group of the socket file after binding. `None` leaves it unchanged.
+    pub fn owner(mut self, uid: Option<u32>, gid: Option<u32>) -> Self {
+        self.owner = Some((uid, gid));
+        self
+    }
+}
+
+// 生存確認のために接続するソケット. 相手が accept しなくても connect で待たないよう非ブロッキングで作る.
+#[cfg(target_os = "linux")]
+fn probe_socket<P: Protocol>(pro: &P) -> io::Result<RawFd> {
+    let ty = pro.socket_type() | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC;
+    Ok(libc_try!(libc::socket(pro.family_type() as i32, ty, pro.protocol_type())))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe_socket<P: Protocol>(pro: &P) -> io::Result<RawFd> {
+    let fd = try!(socket(pro));
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK) } < 0 {
+        let err = io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(err);
+    }
+    Ok(fd)
+}
+
+// 調べたときと同じファイルのときだけ削除する. 間に別のプロセスが bind し直したファイルは消さない.
+fn remove_same_file(path: &Path, md: &fs::Metadata) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(ref now) if now.dev() != md.dev() || now.ino() != md.ino() => Err(io::Error::from_raw_os_error(libc::EADDRINUSE)),
+        Ok(_) => match fs::remove_file(path) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        },
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+// 誰も listen していないソケットファイルだけを削除する.
+fn unlink_stale<P: LocalProtocol>(pro: &P, ep: &LocalEndpoint<P>, path: &Path) -> io::Result<()> {
+    let md = try!(fs::symlink_metadata(path));
+    if !md.file_type().is_socket() {
+        return Err(io::Error::from_raw_os_error(libc::EADDRINUSE));
+    }
+    let fd = try!(probe_socket(pro));
+    let res = unsafe { libc::connect(fd, ep.as_sockaddr(), ep.size() as libc::socklen_t) };
+    let err = io::Error::last_os_error();
+    unsafe { libc::close(fd) };
+    if res == 0 {
+        return Err(io::Error::from_raw_os_error(libc::EADDRINUSE));
+    }
+    match err.raw_os_error() {
+        // backlog が一杯なだけで listen はしている.
+        Some(libc::EAGAIN) | Some(libc::EINPROGRESS) => Err(io::Error::from_raw_os_error(libc::EADDRINUSE)),
+        Some(libc::ECONNREFUSED) => remove_same_file(path, &md),
+        Some(libc::ENOENT) => Ok(()),
+        _ => Err(err),
+    }
+}
+
+fn chown(path: &Path, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    let path = try!(CString::new(path.as_os_str().as_bytes()));
+    libc_try!(libc::chown(path.as_ptr(), uid.unwrap_or(!0), gid.unwrap_or(!0)));
+    Ok(())
+}
+
+impl<P, S> SocketListener<P, S>
+    where P: LocalProtocol + Protocol<Endpoint = LocalEndpoint<P>>,
+          S: FromRawFd<P>,
+{
+    /// Binds the UNIX domain listener with the options.
+    ///
+    /// The options of the socket file are ignored for the abstract namespace.
+    pub fn bind_with(&self, ep: &LocalEndpoint<P>, opts: &LocalBindOptions) -> io::Result<()> {
+        let path = ep.as_path().filter(|path| !path.as_os_str().is_empty());
+        match bind(self, ep) {
+            Err(ref err) if err.raw_os_error() == Some(libc::EADDRINUSE) && opts.unlink_stale && path.is_some() => {
+                try!(unlink_stale(&self.pro, ep, path.unwrap()));
+                try!(bind(self, ep));
+            },
+            res => try!(res),
+        }
+        if let Some(path) = path {
+            if let Some(mode) = opts.mode {
+                try!(fs::set_permissions(path, fs::Permissions::from_mode(mode)));
+            }
+            if let Some((uid, gid)) = opts.owner {
+                try!(chown(path, uid, gid));
+            }
+            if opts.unlink_on_drop {
+                let md = try!(fs::symlink_metadata(path));
+                let sp = Box::into_raw(Box::new(SocketPath {
+                    path: path.to_path_buf(),
+                    dev: md.dev(),
+                    ino: md.ino(),
+                }));
+                // bind は一度しか成功しないので、既に設定されていることはない.
+                if self.path.compare_exchange(ptr::null_mut(), sp, Ordering::AcqRel, Ordering::Acquire).is_err() {
+                    drop(unsafe { Box::from_raw(sp) });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds to an unique address in the abstract namespace chosen by the kernel, and returns it.
+    #[cfg(target_os = "linux")]
+    pub fn autobind(&self) -> io::Result<LocalEndpoint<P>> {
+        try!(autobind(self));
+        self.local_endpoint()
+    }
+}
+
+#[test]
+fn test_bind_with_unlink_stale() {
+    use local::{LocalStream, LocalStreamEndpoint, LocalStreamListener};
+
+    let io = &IoService::new();
+    let path = format!("/tmp/asyncio_unlink_stale_{}.sock", unsafe { libc::getpid() });
+    let _ = fs::remove_file(&path);
+    let ep = LocalStreamEndpoint::new(&path).unwrap();
+
+    // 削除されないソケットファイルを残す.
+    let sv = LocalStreamListener::new(io, LocalStream).unwrap();
+    sv.bind(&ep).unwrap();
+    drop(sv);
+
+    let sv = LocalStreamListener::new(io, LocalStream).unwrap();
+    assert!(sv.bind(&ep).is_err());
+    let opts = LocalBindOptions::new().unlink_stale(true).unlink_on_drop(true);
+    sv.bind_with(&ep, &opts).unwrap();
+    sv.listen().unwrap();
+
+    // listen しているソケットファイルは削除しない.
+    let sv2 = LocalStreamListener::new(io, LocalStream).unwrap();
+    assert_eq!(sv2.bind_with(&ep, &opts).unwrap_err().raw_os_error(), Some(libc::EADDRINUSE));
+    drop(sv2);
+    assert!(fs::metadata(&path).is_ok());
+
+    drop(sv);
+    assert!(fs::metadata(&path).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_unlink_stale_backlog_full() {
+    use local::{LocalStream, LocalStreamEndpoint, LocalStreamListener};
+
+    let io = &IoService::new();
+    let path = format!("/tmp/asyncio_unlink_stale_full_{}.sock", unsafe { libc::getpid() });
+    let _ = fs::remove_file(&path);
+    let ep = LocalStreamEndpoint::new(&path).unwrap();
+    let sv = LocalStreamListener::new(io, LocalStream).unwrap();
+    sv.bind(&ep).unwrap();
+    listen(&sv, 0).unwrap();
+
+    // accept しないまま backlog を埋める.
+    let mut fds = Vec::new();
+    loop {
+        let fd = probe_socket(&LocalStream).unwrap();
+        fds.push(fd);
+        if unsafe { libc::connect(fd, ep.as_sockaddr(), ep.size() as libc::socklen_t) } < 0 {
+            assert_eq!(io::Error::last_os_error().raw_os_error(), Some(libc::EAGAIN));
+            break;
+        }
+    }
+
+    // 接続できなくても listen しているソケットファイルは削除しない.
+    let sv2 = LocalStreamListener::new(io, LocalStream).unwrap();
+    let opts = LocalBindOptions::new().unlink_stale(true);
+    assert_eq!(sv2.bind_with(&ep, &opts).unwrap_err().raw_os_error(), Some(libc::EADDRINUSE));
+    assert!(fs::metadata(&path).is_ok());
+
+    for fd in fds {
+        unsafe { libc::close(fd) };
+    }
+    drop(sv);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_remove_same_file() {
+    use local::{LocalStream, LocalStreamEndpoint, LocalStreamListener};
+
+    let io = &IoService::new();
+    let path = format!("/tmp/asyncio_remove_same_file_{}.sock", unsafe { libc::getpid() });
+    let _ = fs::remove_file(&path);
+    let ep = LocalStreamEndpoint::new(&path).unwrap();
+    let sv1 = LocalStreamListener::new(io, LocalStream).unwrap();
+    sv1.bind(&ep).unwrap();
+    let md = fs::symlink_metadata(&path).unwrap();
+
+    // 調べた後に別のソケットが bind し直したファイルは削除しない.
+    fs::remove_file(&path).unwrap();
+    let sv2 = LocalStreamListener::new(io, LocalStream).unwrap();
+    sv2.bind(&ep).unwrap();
+    let path = Path::new(&path);
+    assert_eq!(remove_same_file(path, &md).unwrap_err().raw_os_error(), Some(libc::EADDRINUSE));
+    assert!(fs::symlink_metadata(path).is_ok());
+
+    let md = fs::symlink_metadata(path).unwrap();
+    remove_same_file(path, &md).unwrap();
+    assert!(fs::symlink_metadata(path).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_autobind() {
+    use local::{LocalSeqPacket, LocalSeqPacketListener};
+
+    let io = &IoService::new();
+    let sv = LocalSeqPacketListener::new(io, LocalSeqPacket).unwrap();
+    let ep = sv.autobind().unwrap();
+    assert!(ep.is_abstract());
+    assert_eq!(sv.local_endpoint().unwrap(), ep);
+}
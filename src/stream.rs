@@ -1,6 +1,6 @@
-use std::io;
-use error::ErrCode;
-use unsafe_cell::{UnsafeRefCell};
+use std::io::{self, IoSlice, IoSliceMut};
+use error::{ErrCode, eof, write_zero};
+use unsafe_cell::{UnsafeRefCell, UnsafeSliceCell};
 use io_service::{IoObject, IoService, Callback, Handler, AsyncResult};
 use streambuf::{StreamBuf};
 
@@ -14,6 +14,31 @@ pub trait Stream : IoObject + Send + 'static {
     fn read_some(&self, buf: &mut [u8]) -> io::Result<usize>;
 
     fn write_some(&self, buf: &[u8]) -> io::Result<usize>;
+
+    // 既定ではベクタ I/O を使わず、最初の空でないバッファだけを読み書きする.
+    fn async_read_some_vectored<F>(&self, bufs: &mut [IoSliceMut], handler: F) -> F::Output
+        where F: Handler<usize>
+    {
+        let buf = bufs.iter_mut().find(|buf| !buf.is_empty()).map_or(&mut [][..], |buf| &mut **buf);
+        self.async_read_some(buf, handler)
+    }
+
+    fn async_write_some_vectored<F>(&self, bufs: &[IoSlice], handler: F) -> F::Output
+        where F: Handler<usize>
+    {
+        let buf = bufs.iter().find(|buf| !buf.is_empty()).map_or(&[][..], |buf| &**buf);
+        self.async_write_some(buf, handler)
+    }
+
+    fn read_some_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        let buf = bufs.iter_mut().find(|buf| !buf.is_empty()).map_or(&mut [][..], |buf| &mut **buf);
+        self.read_some(buf)
+    }
+
+    fn write_some_vectored(&self, bufs: &[IoSlice]) -> io::Result<usize> {
+        let buf = bufs.iter().find(|buf| !buf.is_empty()).map_or(&[][..], |buf| &**buf);
+        self.write_some(buf)
+    }
 }
 
 pub fn read_until<S, M>(s: &S, sbuf: &mut StreamBuf, mut cond: M) -> io::Result<usize>
@@ -216,6 +241,215 @@ pub fn async_write_until<S, M, F>(s: &S, sbuf: &mut StreamBuf, mut cond: M, hand
     async_write_until_detail(s, sbuf, total, handler, total)
 }
 
+/// Reads until the buffer is filled.
+///
+/// Returns an `UnexpectedEof` error if the stream is closed before the buffer is filled.
+pub fn read_exact<S>(s: &S, buf: &mut [u8]) -> io::Result<usize>
+    where S: Stream,
+{
+    let mut cur = 0;
+    while cur < buf.len() {
+        match try!(s.read_some(&mut buf[cur..])) {
+            0 => return Err(eof()),
+            len => cur += len,
+        }
+    }
+    Ok(cur)
+}
+
+struct ReadExactHandler<S, F> {
+    s: UnsafeRefCell<S>,
+    buf: UnsafeSliceCell<u8>,
+    handler: F,
+    cur: usize,
+}
+
+impl<S, F> Handler<usize> for ReadExactHandler<S, F>
+    where S: Stream,
+          F: Handler<usize>,
+{
+    type Output = F::Output;
+
+    fn callback(self, io: &IoService, res: io::Result<usize>) {
+        let ReadExactHandler { s, mut buf, handler, cur } = self;
+        let s = unsafe { s.as_ref() };
+        match res {
+            Ok(0) => handler.callback(io, Err(eof())),
+            Ok(len) => {
+                let buf = unsafe { buf.as_mut_slice() };
+                async_read_exact_detail(s, buf, handler, cur + len);
+            },
+            Err(err) => handler.callback(io, Err(err)),
+        }
+    }
+
+    fn wrap<G>(self, callback: G) -> Callback
+        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static,
+    {
+        let ReadExactHandler { s, buf, handler, cur } = self;
+        handler.wrap(move |io, ec, handler| {
+            callback(io, ec, ReadExactHandler {
+                s: s,
+                buf: buf,
+                handler: handler,
+                cur: cur,
+            })
+        })
+    }
+
+    type AsyncResult = F::AsyncResult;
+
+    fn async_result(&self) -> Self::AsyncResult {
+        self.handler.async_result()
+    }
+}
+
+fn async_read_exact_detail<S, F>(s: &S, buf: &mut [u8], handler: F, cur: usize) -> F::Output
+    where S: Stream,
+          F: Handler<usize>,
+{
+    let io = s.io_service();
+    let out = handler.async_result();
+    if cur == buf.len() {
+        handler.callback(io, Ok(cur));
+    } else {
+        let handler = ReadExactHandler {
+            s: UnsafeRefCell::new(s),
+            buf: UnsafeSliceCell::new(buf),
+            handler: handler,
+            cur: cur,
+        };
+        s.async_read_some(&mut buf[cur..], handler);
+    }
+    out.get(io)
+}
+
+/// Asynchronously reads until the buffer is filled.
+///
+/// The handler is called with the length of the buffer, or an `UnexpectedEof` error if the stream is
+/// closed before the buffer is filled.
+///
+/// # Examples
+/// ```
+/// use std::io;
+/// use std::sync::Arc;
+/// use asyncio::{IoService, Stream, wrap, async_read_exact};
+/// use asyncio::local::{LocalStream, LocalStreamSocket, connect_pair};
+///
+/// let io = &IoService::new();
+/// let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+/// let rx = Arc::new(rx);
+///
+/// let mut buf = [0; 10];
+/// async_read_exact(&*rx, &mut buf, wrap(|_: Arc<LocalStreamSocket>, res: io::Result<usize>| {
+///     assert_eq!(res.unwrap(), 10);
+/// }, &rx));
+/// tx.write_some(b"hello").unwrap();
+/// tx.write_some(b"world").unwrap();
+/// io.run();
+/// assert_eq!(&buf, b"helloworld");
+/// ```
+pub fn async_read_exact<S, F>(s: &S, buf: &mut [u8], handler: F) -> F::Output
+    where S: Stream,
+          F: Handler<usize>,
+{
+    async_read_exact_detail(s, buf, handler, 0)
+}
+
+/// Writes the entire buffer.
+///
+/// Returns a `WriteZero` error if the stream can't accept any more bytes.
+pub fn write_all<S>(s: &S, buf: &[u8]) -> io::Result<usize>
+    where S: Stream,
+{
+    let mut cur = 0;
+    while cur < buf.len() {
+        match try!(s.write_some(&buf[cur..])) {
+            0 => return Err(write_zero()),
+            len => cur += len,
+        }
+    }
+    Ok(cur)
+}
+
+struct WriteAllHandler<S, F> {
+    s: UnsafeRefCell<S>,
+    buf: UnsafeSliceCell<u8>,
+    handler: F,
+    cur: usize,
+}
+
+impl<S, F> Handler<usize> for WriteAllHandler<S, F>
+    where S: Stream,
+          F: Handler<usize>,
+{
+    type Output = F::Output;
+
+    fn callback(self, io: &IoService, res: io::Result<usize>) {
+        let WriteAllHandler { s, buf, handler, cur } = self;
+        let s = unsafe { s.as_ref() };
+        match res {
+            Ok(0) => handler.callback(io, Err(write_zero())),
+            Ok(len) => {
+                let buf = unsafe { buf.as_slice() };
+                async_write_all_detail(s, buf, handler, cur + len);
+            },
+            Err(err) => handler.callback(io, Err(err)),
+        }
+    }
+
+    fn wrap<G>(self, callback: G) -> Callback
+        where G: FnOnce(&IoService, ErrCode, Self) + Send + 'static,
+    {
+        let WriteAllHandler { s, buf, handler, cur } = self;
+        handler.wrap(move |io, ec, handler| {
+            callback(io, ec, WriteAllHandler {
+                s: s,
+                buf: buf,
+                handler: handler,
+                cur: cur,
+            })
+        })
+    }
+
+    type AsyncResult = F::AsyncResult;
+
+    fn async_result(&self) -> Self::AsyncResult {
+        self.handler.async_result()
+    }
+}
+
+fn async_write_all_detail<S, F>(s: &S, buf: &[u8], handler: F, cur: usize) -> F::Output
+    where S: Stream,
+          F: Handler<usize>,
+{
+    let io = s.io_service();
+    let out = handler.async_result();
+    if cur == buf.len() {
+        handler.callback(io, Ok(cur));
+    } else {
+        let handler = WriteAllHandler {
+            s: UnsafeRefCell::new(s),
+            buf: UnsafeSliceCell::new(buf),
+            handler: handler,
+            cur: cur,
+        };
+        s.async_write_some(&buf[cur..], handler);
+    }
+    out.get(io)
+}
+
+/// Asynchronously writes the entire buffer.
+///
+/// The handler is called with the length of the buffer, or a `WriteZero` error if the stream can't
+/// accept any more bytes.
+pub fn async_write_all<S, F>(s: &S, buf: &[u8], handler: F) -> F::Output
+    where S: Stream,
+          F: Handler<usize>,
+{
+    async_write_all_detail(s, buf, handler, 0)
+}
+
 
 pub trait MatchCondition : Send + 'static {
     fn match_cond(&mut self, buf: &[u8]) -> Result<usize, usize>;
@@ -290,3 +524,65 @@ fn test_match_cond() {
     assert!("".match_cond("hello".as_bytes()) == Err(0));
     assert!("l".match_cond("hello".as_bytes()) == Ok(3));
 }
+
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+#[cfg(test)]
+use io_service::wrap;
+#[cfg(test)]
+use local::{LocalStream, LocalStreamSocket, connect_pair};
+
+#[test]
+fn test_read_exact_eof() {
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    tx.write_some(b"hello").unwrap();
+    drop(tx);
+    let mut buf = [0; 10];
+    assert_eq!(read_exact(&rx, &mut buf).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_async_write_all() {
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let (rx, tx) = (Arc::new(rx), Arc::new(tx));
+    let res = Arc::new(Mutex::new(Vec::new()));
+
+    // ソケットのバッファより大きいので複数回に分けて書き込まれる.
+    let data = vec![1u8; 1024 * 1024];
+    let mut buf = vec![0u8; 1024 * 1024];
+    let res_ = res.clone();
+    async_write_all(&*tx, &data, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        res_.lock().unwrap().push(r.unwrap());
+    }, &tx));
+    let res_ = res.clone();
+    async_read_exact(&*rx, &mut buf, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        res_.lock().unwrap().push(r.unwrap());
+    }, &rx));
+    io.run();
+    assert_eq!(*res.lock().unwrap(), [data.len(), data.len()]);
+    assert!(buf == data);
+}
+
+#[test]
+fn test_default_vectored() {
+    use std::io::{IoSlice, IoSliceMut};
+    use libc;
+    use posix::StreamDescriptor;
+
+    let io = &IoService::new();
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let rx = unsafe { StreamDescriptor::from_raw_fd(io, fds[0]) };
+    let tx = unsafe { StreamDescriptor::from_raw_fd(io, fds[1]) };
+
+    // 既定の実装は最初の空でないバッファだけを使う.
+    assert_eq!(tx.write_some_vectored(&[IoSlice::new(b""), IoSlice::new(b"head"), IoSlice::new(b"body")]).unwrap(), 4);
+    let mut empty = [0; 0];
+    let mut head = [0; 8];
+    let mut body = [0; 8];
+    assert_eq!(rx.read_some_vectored(&mut [IoSliceMut::new(&mut empty), IoSliceMut::new(&mut head), IoSliceMut::new(&mut body)]).unwrap(), 4);
+    assert_eq!(&head[..4], b"head");
+    assert_eq!(body, [0; 8]);
+}
@@ -1,9 +1,11 @@
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut};
 use io_service::{IoObject, FromRawFd, IoService, IoActor, Handler};
 use traits::{Protocol, IoControl, GetSocketOption, SetSocketOption, Shutdown};
 use stream::{Stream};
 use fd_ops::*;
 use socket_base::BytesReadable;
+#[cfg(target_os = "linux")]
+use posix::StreamDescriptor;
 
 /// Provides a stream-oriented socket.
 pub struct StreamSocket<P: Protocol> {
@@ -29,12 +31,24 @@ impl<P: Protocol> StreamSocket<P> {
         async_recv(self, buf, flags, handler)
     }
 
+    pub fn async_receive_vectored<F>(&self, bufs: &mut [IoSliceMut], flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_recvmsg(self, bufs, flags, handler)
+    }
+
     pub fn async_send<F>(&self, buf: &[u8], flags: i32, handler: F) -> F::Output
         where F: Handler<usize>,
     {
         async_send(self, buf, flags, handler)
     }
 
+    pub fn async_send_vectored<F>(&self, bufs: &[IoSlice], flags: i32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_sendmsg(self, bufs, flags, handler)
+    }
+
     pub fn available(&self) -> io::Result<usize> {
         let mut bytes = BytesReadable::default();
         try!(self.io_control(&mut bytes));
@@ -81,6 +95,10 @@ impl<P: Protocol> StreamSocket<P> {
         recv(self, buf, flags)
     }
 
+    pub fn receive_vectored(&self, bufs: &mut [IoSliceMut], flags: i32) -> io::Result<usize> {
+        recvmsg(self, bufs, flags)
+    }
+
     pub fn remote_endpoint(&self) -> io::Result<P::Endpoint> {
         getpeername(self, unsafe { self.pro.uninitialized() })
     }
@@ -89,6 +107,10 @@ impl<P: Protocol> StreamSocket<P> {
         send(self, buf, flags)
     }
 
+    pub fn send_vectored(&self, bufs: &[IoSlice], flags: i32) -> io::Result<usize> {
+        sendmsg(self, bufs, flags)
+    }
+
     pub fn set_non_blocking(&self, on: bool) -> io::Result<()> {
         setnonblock(self, on)
     }
@@ -104,6 +126,58 @@ impl<P: Protocol> StreamSocket<P> {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl<P: Protocol> StreamSocket<P> {
+    /// Asynchronously sends up to `len` bytes of `file` without copying through userspace.
+    ///
+    /// If `offset` is given, the data is read from there and the file position is not changed.
+    /// Otherwise the data is read from the current file position, and it is advanced.
+    /// The handler receives the number of bytes sent, which may be less than `len`,
+    /// and 0 if `file` is at end.
+    pub fn async_send_file<T, F>(&self, file: &T, offset: Option<u64>, len: usize, handler: F) -> F::Output
+        where T: AsRawFd,
+              F: Handler<usize>,
+    {
+        async_sendfile(self, file.as_raw_fd(), offset, len, handler)
+    }
+
+    /// Asynchronously moves up to `len` bytes from the pipe into the socket without copying through userspace.
+    ///
+    /// The operation waits for the pipe to have data and for the socket to become writable.
+    /// The pipe should be registered to the same `IoService` as the socket,
+    /// and while waiting for the pipe, the operation is canceled by `cancel()` of the pipe.
+    pub fn async_splice_from<F>(&self, pipe: &StreamDescriptor, len: usize, flags: u32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_splice_from(self, pipe, len, flags, handler)
+    }
+
+    /// Asynchronously moves up to `len` bytes from the socket into the pipe without copying through userspace.
+    ///
+    /// The operation waits for the pipe to have room and for the socket to become readable.
+    /// The pipe should be registered to the same `IoService` as the socket,
+    /// and while waiting for the pipe, the operation is canceled by `cancel()` of the pipe.
+    pub fn async_splice_to<F>(&self, pipe: &StreamDescriptor, len: usize, flags: u32, handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_splice_to(self, pipe, len, flags, handler)
+    }
+
+    pub fn send_file<T>(&self, file: &T, offset: Option<u64>, len: usize) -> io::Result<usize>
+        where T: AsRawFd,
+    {
+        sendfile(self, file.as_raw_fd(), offset, len)
+    }
+
+    pub fn splice_from(&self, pipe: &StreamDescriptor, len: usize, flags: u32) -> io::Result<usize> {
+        splice_from(self, pipe, len, flags)
+    }
+
+    pub fn splice_to(&self, pipe: &StreamDescriptor, len: usize, flags: u32) -> io::Result<usize> {
+        splice_to(self, pipe, len, flags)
+    }
+}
+
 impl<P: Protocol> Stream for StreamSocket<P> {
     fn async_read_some<F>(&self, buf: &mut [u8], handler: F) -> F::Output
         where F: Handler<usize>,
@@ -124,6 +198,26 @@ impl<P: Protocol> Stream for StreamSocket<P> {
     fn write_some(&self, buf: &[u8]) -> io::Result<usize> {
         write(self, buf)
     }
+
+    fn async_read_some_vectored<F>(&self, bufs: &mut [IoSliceMut], handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_readv(self, bufs, handler)
+    }
+
+    fn async_write_some_vectored<F>(&self, bufs: &[IoSlice], handler: F) -> F::Output
+        where F: Handler<usize>,
+    {
+        async_writev(self, bufs, handler)
+    }
+
+    fn read_some_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        readv(self, bufs)
+    }
+
+    fn write_some_vectored(&self, bufs: &[IoSlice]) -> io::Result<usize> {
+        writev(self, bufs)
+    }
 }
 
 unsafe impl<P: Protocol> IoObject for StreamSocket<P> {
@@ -195,3 +289,199 @@ fn test_send_error_of_non_connect() {
 
     io.run();
 }
+
+#[test]
+fn test_vectored_io() {
+    use std::sync::{Arc, Mutex};
+    use {IoService, wrap};
+    use local::{LocalStream, LocalStreamSocket, connect_pair};
+
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let rx = Arc::new(rx);
+
+    assert_eq!(tx.write_some_vectored(&[IoSlice::new(b"head"), IoSlice::new(b"payload")]).unwrap(), 11);
+    assert_eq!(tx.send_vectored(&[IoSlice::new(b"HEAD"), IoSlice::new(b"PAYLOAD")], 0).unwrap(), 11);
+
+    let mut head = [0; 4];
+    let mut body = [0; 7];
+    assert_eq!(rx.read_some_vectored(&mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut body)]).unwrap(), 11);
+    assert_eq!((&head, &body), (b"head", b"payload"));
+
+    let res = Arc::new(Mutex::new(None));
+    let res_ = res.clone();
+    let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut body)];
+    rx.async_receive_vectored(&mut bufs, 0, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        *res_.lock().unwrap() = Some(r);
+    }, &rx));
+    io.run();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 11);
+    assert_eq!((&head, &body), (b"HEAD", b"PAYLOAD"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_send_file() {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use libc;
+    use {IoService, wrap};
+    use local::{LocalStream, LocalStreamSocket, connect_pair};
+
+    let path = format!("/tmp/asyncio_send_file_{}", unsafe { libc::getpid() });
+    File::create(&path).unwrap().write_all(b"0123456789").unwrap();
+    let file = File::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let io = &IoService::new();
+    let (rx, tx): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let tx = Arc::new(tx);
+
+    assert_eq!(tx.send_file(&file, Some(2), 3).unwrap(), 3);
+    let res = Arc::new(Mutex::new(None));
+    let res_ = res.clone();
+    tx.async_send_file(&file, None, 100, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        *res_.lock().unwrap() = Some(r);
+    }, &tx));
+    io.run();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 10);
+
+    io.reset();
+    let mut buf = [0; 16];
+    assert_eq!(rx.read_some(&mut buf).unwrap(), 13);
+    assert_eq!(&buf[..13], b"2340123456789");
+
+    // 入力の終端では同期でも非同期でも 0 を返す.
+    assert_eq!(tx.send_file(&file, None, 100).unwrap(), 0);
+    let res_ = res.clone();
+    tx.async_send_file(&file, None, 100, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        *res_.lock().unwrap() = Some(r);
+    }, &tx));
+    io.run();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 0);
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+fn pipe(io: &IoService) -> (StreamDescriptor, StreamDescriptor) {
+    use libc;
+
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    unsafe { (StreamDescriptor::from_raw_fd(io, fds[0]), StreamDescriptor::from_raw_fd(io, fds[1])) }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_splice() {
+    use std::sync::{Arc, Mutex};
+    use {IoService, wrap};
+    use local::{LocalStream, LocalStreamSocket, connect_pair};
+
+    let io = &IoService::new();
+    let (pipe_r, pipe_w) = pipe(io);
+    let pipe_r = Arc::new(pipe_r);
+    let (src, src_peer): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let (dst, dst_peer): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let src = Arc::new(src);
+    let dst = Arc::new(dst);
+
+    // socket -> pipe -> socket
+    let res = Arc::new(Mutex::new(None));
+    let res_ = res.clone();
+    let dst_ = dst.clone();
+    let pipe_r_ = pipe_r.clone();
+    src.async_splice_to(&pipe_w, 4096, 0, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        let len = r.unwrap();
+        assert_eq!(len, 5);
+        let res_ = res_.clone();
+        dst_.async_splice_from(&*pipe_r_, len, 0, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+            *res_.lock().unwrap() = Some(r);
+        }, &dst_));
+    }, &src));
+    src_peer.write_some(b"hello").unwrap();
+    io.run();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 5);
+
+    io.reset();
+    let mut buf = [0; 8];
+    assert_eq!(dst_peer.read_some(&mut buf).unwrap(), 5);
+    assert_eq!(&buf[..5], b"hello");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_splice_from_empty_pipe() {
+    use std::sync::{Arc, Mutex};
+    use {IoService, wrap};
+    use local::{LocalStream, LocalStreamSocket, connect_pair};
+
+    let io = &IoService::new();
+    let (pipe_r, pipe_w) = pipe(io);
+    let (dst, dst_peer): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let dst = Arc::new(dst);
+
+    // ソケットは書き込めるがパイプが空なので、パイプにデータが届くまで待つ.
+    let res = Arc::new(Mutex::new(None));
+    let res_ = res.clone();
+    dst.async_splice_from(&pipe_r, 16, 0, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        *res_.lock().unwrap() = Some(r);
+    }, &dst));
+    io.poll();
+    assert!(res.lock().unwrap().is_none());
+    assert_eq!(io.stats().pending_input_ops, 1);
+
+    io.reset();
+    pipe_w.write_some(b"abc").unwrap();
+    io.run();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 3);
+
+    io.reset();
+    let mut buf = [0; 8];
+    assert_eq!(dst_peer.read_some(&mut buf).unwrap(), 3);
+    assert_eq!(&buf[..3], b"abc");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_splice_to_full_pipe() {
+    use std::sync::{Arc, Mutex};
+    use {IoService, wrap};
+    use local::{LocalStream, LocalStreamSocket, connect_pair};
+
+    let io = &IoService::new();
+    let (pipe_r, pipe_w) = pipe(io);
+    let (src, src_peer): (LocalStreamSocket, LocalStreamSocket) = connect_pair(io, LocalStream).unwrap();
+    let src = Arc::new(src);
+
+    // パイプを一杯にしておく.
+    pipe_w.set_non_blocking(true).unwrap();
+    let chunk = [0; 4096];
+    let mut filled = 0;
+    while let Ok(len) = pipe_w.write_some(&chunk) {
+        filled += len;
+    }
+
+    let res = Arc::new(Mutex::new(None));
+    let res_ = res.clone();
+    src.async_splice_to(&pipe_w, 16, 0, wrap(move |_: Arc<LocalStreamSocket>, r: io::Result<usize>| {
+        *res_.lock().unwrap() = Some(r);
+    }, &src));
+    src_peer.write_some(b"hello").unwrap();
+    io.poll();
+    assert!(res.lock().unwrap().is_none());
+
+    // パイプが空くと splice する.
+    io.reset();
+    let mut buf = [0; 4096];
+    while filled > 0 {
+        filled -= pipe_r.read_some(&mut buf).unwrap();
+    }
+    io.run();
+    assert_eq!(res.lock().unwrap().take().unwrap().unwrap(), 5);
+
+    io.reset();
+    assert_eq!(pipe_r.read_some(&mut buf).unwrap(), 5);
+    assert_eq!(&buf[..5], b"hello");
+}
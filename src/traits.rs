@@ -64,6 +64,10 @@ pub trait SocketOption<P> {
 pub trait GetSocketOption<P> : SocketOption<P> + Default {
     fn data_mut(&mut self) -> &mut Self::Data;
 
+    fn capacity(&self) -> usize {
+        mem::size_of::<Self::Data>()
+    }
+
     fn resize(&mut self, _size: usize) {
     }
 }
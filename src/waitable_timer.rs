@@ -3,7 +3,7 @@ use std::thread;
 use std::marker::PhantomData;
 use std::time::Duration;
 use error::{READY, ECANCELED, stopped};
-use io_service::{IoObject, IoService, Handler, AsyncResult, TimerActor};
+use io_service::{IoObject, IoService, Callback, Handler, AsyncResult, TimerActor};
 use clock::{Clock, SteadyClock, SystemClock, Expiry};
 
 /// Provides waitable timer functionality.
@@ -34,7 +34,7 @@ impl<C: Clock> WaitableTimer<C> {
 
     pub fn cancel(&self) {
         if let Some(callback) = self.act.unset_wait() {
-            self.io_service().dispatch(move |io| callback(io, ECANCELED));
+            self.io_service().dispatch(move |io| callback.call(io, ECANCELED));
         }
     }
 
@@ -57,7 +57,7 @@ fn async_wait<F>(act: &TimerActor, expiry: Expiry, handler: F) -> F::Output
     where F: Handler<()>
 {
     let out = handler.async_result();
-    act.set_wait(expiry, Box::new(move |io: *const IoService, ec| {
+    act.set_wait(expiry, Callback::new(move |io: *const IoService, ec| {
         let io = unsafe { &*io };
         match ec {
             READY => handler.callback(io, Ok(())),